@@ -0,0 +1,304 @@
+//! A stand-in for the parts of Helix and the Twitch OAuth endpoints the GUI uses, serving canned data.
+//!
+//! Run it with an optional port (default 8080), then start the GUI with the environment it prints.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use url::Url;
+
+const USER_ID: &str = "100";
+const USER_LOGIN: &str = "mockviewer";
+const THUMBNAIL: &[u8] = include_bytes!("../../icon.png");
+
+/// `id`, `name`
+const GAMES: &[(&str, &str)] = &[
+    ("300", "Just Chatting"),
+    ("301", "Celeste"),
+    ("302", "Super Metroid"),
+    ("303", "Art"),
+    ("304", "Minecraft"),
+    ("305", "Elden Ring"),
+];
+/// `login`, `display name`, `game`, `title`, `viewers`, `minutes live`
+const STREAMS: &[(&str, &str, &str, &str, usize, u64)] = &[
+    ("mockstreamer", "MockStreamer", "Just Chatting", "Testing the stream grid", 1234, 95),
+    ("speedrunner", "SpeedRunner", "Celeste", "Any% practice, then some long titles that need wrapping to fit", 87, 20),
+    ("retrogamer", "RetroGamer", "Super Metroid", "Chill 100% run", 4521, 340),
+    ("pixelartist", "PixelArtist", "Art", "Drawing sprites for a game jam", 12, 5),
+    ("strawberryjam", "StrawberryJam", "Celeste", "Modded maps", 310, 150),
+];
+
+/// Followed channels that aren't live: `login`, `display name`, `last game`, `days since the last stream`
+const OFFLINE_CHANNELS: &[(&str, &str, &str, u64)] = &[
+    ("sleepyhead", "SleepyHead", "Minecraft", 1),
+    ("weekendwarrior", "WeekendWarrior", "Elden Ring", 6),
+];
+
+/// Every followed channel, live or not: `user ID`, `login`, `display name`, `game`
+fn channels() -> Vec<(String, &'static str, &'static str, &'static str)> {
+    let live = STREAMS
+        .iter()
+        .enumerate()
+        .map(|(i, (login, name, game, ..))| ((200 + i).to_string(), *login, *name, *game));
+    let offline = OFFLINE_CHANNELS
+        .iter()
+        .enumerate()
+        .map(|(i, (login, name, game, _))| ((210 + i).to_string(), *login, *name, *game));
+    live.chain(offline).collect()
+}
+
+fn game_id(game: &str) -> &'static str {
+    GAMES.iter().find(|(_, name)| *name == game).map_or("", |(id, _)| id)
+}
+
+/// Formats a Unix timestamp as RFC 3339, the way Helix does.
+fn rfc3339(unix_seconds: u64) -> String {
+    let days = (unix_seconds / 86400) as i64;
+    let seconds_of_day = unix_seconds % 86400;
+    // Howard Hinnant's days-to-civil algorithm.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60
+    )
+}
+
+fn followed_streams(base_url: &str) -> String {
+    let now = now();
+    let data: Vec<String> = STREAMS
+        .iter()
+        .enumerate()
+        .map(|(i, (login, name, game, title, viewers, minutes))| {
+            format!(
+                r#"{{"id":"{}","user_id":"{}","user_login":"{login}","user_name":"{name}","game_id":"{}","game_name":"{game}","type":"live","title":"{title}","tags":["English","Mock"],"viewer_count":{viewers},"started_at":"{}","language":"en","thumbnail_url":"{base_url}thumbnails/{login}-{{width}}x{{height}}.png","tag_ids":[],"is_mature":{}}}"#,
+                9000 + i,
+                200 + i,
+                game_id(game),
+                rfc3339(now - minutes * 60),
+                i == 2
+            )
+        })
+        .collect();
+    format!(r#"{{"data":[{}],"pagination":{{}}}}"#, data.join(","))
+}
+
+fn games(base_url: &str, ids: &[String]) -> String {
+    let data: Vec<String> = GAMES
+        .iter()
+        .filter(|(id, _)| ids.iter().any(|wanted| wanted == id))
+        .map(|(id, name)| {
+            format!(r#"{{"id":"{id}","name":"{name}","box_art_url":"{base_url}boxart/{id}-{{width}}x{{height}}.png","igdb_id":""}}"#)
+        })
+        .collect();
+    format!(r#"{{"data":[{}],"pagination":{{}}}}"#, data.join(","))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+fn followed_channels() -> String {
+    let data: Vec<String> = channels()
+        .iter()
+        .map(|(id, login, name, _)| {
+            format!(
+                r#"{{"broadcaster_id":"{id}","broadcaster_login":"{login}","broadcaster_name":"{name}","followed_at":"{}"}}"#,
+                rfc3339(now() - 90 * 86400)
+            )
+        })
+        .collect();
+    format!(r#"{{"data":[{}],"total":{},"pagination":{{}}}}"#, data.join(","), data.len())
+}
+
+fn channel_information(ids: &[String]) -> String {
+    let data: Vec<String> = channels()
+        .iter()
+        .filter(|(id, ..)| ids.contains(id))
+        .map(|(id, login, name, game)| {
+            format!(
+                r#"{{"broadcaster_id":"{id}","broadcaster_login":"{login}","broadcaster_name":"{name}","broadcaster_language":"en","game_id":"{}","game_name":"{game}","title":"Mock channel","delay":0,"tags":["Mock"],"content_classification_labels":[],"is_branded_content":false}}"#,
+                game_id(game)
+            )
+        })
+        .collect();
+    format!(r#"{{"data":[{}]}}"#, data.join(","))
+}
+
+fn users(base_url: &str, ids: &[String]) -> String {
+    let data: Vec<String> = channels()
+        .iter()
+        .filter(|(id, ..)| ids.contains(id))
+        .map(|(id, login, name, game)| {
+            format!(
+                r#"{{"id":"{id}","login":"{login}","display_name":"{name}","type":"","broadcaster_type":"affiliate","description":"{name} streams {game} on the mock server.","profile_image_url":"{base_url}avatars/{login}.png","offline_image_url":"","view_count":0,"created_at":"{}"}}"#,
+                rfc3339(now() - 365 * 86400)
+            )
+        })
+        .collect();
+    format!(r#"{{"data":[{}]}}"#, data.join(","))
+}
+
+/// The last past broadcast of `user_id`, for offline channels.
+fn videos(base_url: &str, user_id: &str) -> String {
+    let channels = channels();
+    let last_stream = OFFLINE_CHANNELS
+        .iter()
+        .zip(channels.iter().skip(STREAMS.len()))
+        .find(|(_, (id, ..))| id == user_id);
+    let data = match last_stream {
+        Some(((login, name, game, days), (id, ..))) => format!(
+            r#"{{"id":"{}","stream_id":null,"user_id":"{id}","user_login":"{login}","user_name":"{name}","title":"Playing {game}","description":"","created_at":"{}","published_at":"{}","url":"https://www.twitch.tv/videos/1","thumbnail_url":"{base_url}thumbnails/{login}-%{{width}}x%{{height}}.png","viewable":"public","view_count":10,"language":"en","type":"archive","duration":"2h3m4s","muted_segments":null}}"#,
+            7000 + days,
+            rfc3339(now() - days * 86400),
+            rfc3339(now() - days * 86400)
+        ),
+        None => String::new(),
+    };
+    format!(r#"{{"data":[{data}],"pagination":{{}}}}"#)
+}
+
+struct Request {
+    method: String,
+    url: Url,
+}
+
+async fn read_request(stream: &mut TcpStream) -> Option<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await.ok()?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let url = Url::parse("http://localhost").ok()?.join(parts.next()?).ok()?;
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await.ok()? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await.ok()?;
+    Some(Request { method, url })
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, headers: &[(&str, &str)], body: &[u8]) {
+    let mut response = format!("HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n", body.len());
+    for (name, value) in headers {
+        response.push_str(&format!("{name}: {value}\r\n"));
+    }
+    response.push_str("\r\n");
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.write_all(body).await;
+    let _ = stream.flush().await;
+}
+
+async fn handle(mut stream: TcpStream, base_url: String) {
+    let Some(request) = read_request(&mut stream).await else {
+        return;
+    };
+    let json = [("Content-Type", "application/json")];
+    let token = r#"{"access_token":"mock-access-token","refresh_token":"mock-refresh-token","expires_in":14400,"scope":["user:read:follows"],"token_type":"bearer"}"#;
+    let query_all = |key: &str| -> Vec<String> {
+        request
+            .url
+            .query_pairs()
+            .filter(|(k, _)| k == key)
+            .map(|(_, v)| v.to_string())
+            .collect()
+    };
+    let query = |key: &str| {
+        request
+            .url
+            .query_pairs()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.to_string())
+            .unwrap_or_default()
+    };
+    match (request.method.as_str(), request.url.path()) {
+        ("GET", "/helix/streams/followed") => {
+            respond(&mut stream, "200 OK", &json, followed_streams(&base_url).as_bytes()).await
+        }
+        ("GET", "/helix/games") => {
+            respond(&mut stream, "200 OK", &json, games(&base_url, &query_all("id")).as_bytes()).await
+        }
+        ("GET", "/helix/channels/followed") => {
+            respond(&mut stream, "200 OK", &json, followed_channels().as_bytes()).await
+        }
+        ("GET", "/helix/channels") => {
+            let body = channel_information(&query_all("broadcaster_id"));
+            respond(&mut stream, "200 OK", &json, body.as_bytes()).await
+        }
+        ("GET", "/helix/users") => {
+            respond(&mut stream, "200 OK", &json, users(&base_url, &query_all("id")).as_bytes()).await
+        }
+        ("GET", "/helix/videos") => {
+            respond(&mut stream, "200 OK", &json, videos(&base_url, &query("user_id")).as_bytes()).await
+        }
+        ("GET", path)
+            if path.starts_with("/thumbnails/") || path.starts_with("/boxart/") || path.starts_with("/avatars/") =>
+        {
+            respond(&mut stream, "200 OK", &[("Content-Type", "image/png")], THUMBNAIL).await
+        }
+        ("GET", "/oauth2/validate") => {
+            let body = format!(r#"{{"client_id":"mock-client-id","login":"{USER_LOGIN}","user_id":"{USER_ID}","scopes":["user:read:follows"],"expires_in":5000000}}"#);
+            respond(&mut stream, "200 OK", &json, body.as_bytes()).await
+        }
+        ("GET", "/oauth2/authorize") => {
+            // Approve straight away, the way Twitch does for an app the user already authorized.
+            let location = format!(
+                "{}#access_token=mock-access-token&scope=user%3Aread%3Afollows&state={}&token_type=bearer",
+                query("redirect_uri"),
+                query("state")
+            );
+            respond(&mut stream, "302 Found", &[("Location", location.as_str())], b"").await
+        }
+        ("POST", "/oauth2/device") => {
+            let body = format!(r#"{{"device_code":"mock-device-code","user_code":"MOCKCODE","verification_uri":"{base_url}activate","expires_in":1800,"interval":1}}"#);
+            respond(&mut stream, "200 OK", &json, body.as_bytes()).await
+        }
+        ("POST", "/oauth2/token") => respond(&mut stream, "200 OK", &json, token.as_bytes()).await,
+        ("POST", "/oauth2/revoke") => respond(&mut stream, "200 OK", &[], b"").await,
+        _ => {
+            let body = r#"{"error":"Not Found","status":404,"message":"not mocked"}"#;
+            respond(&mut stream, "404 Not Found", &json, body.as_bytes()).await
+        }
+    }
+}
+
+/// Answers requests on `listener` until accepting a connection fails.  The GUI's tests run it in-process.
+pub async fn serve(listener: TcpListener) -> std::io::Result<()> {
+    let base_url = format!("http://127.0.0.1:{}/", listener.local_addr()?.port());
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(handle(stream, base_url.clone()));
+    }
+}
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let port: u16 = std::env::args()
+        .nth(1)
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(8080);
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    let base_url = format!("http://127.0.0.1:{}/", listener.local_addr()?.port());
+    println!("Mock Twitch listening on {base_url}");
+    println!("TWITCH_HELIX_URL={base_url}helix/ TWITCH_OAUTH2_URL={base_url}oauth2/");
+    serve(listener).await
+}
@@ -0,0 +1,53 @@
+use std::path::Path;
+
+/// Broadcasters the user picked out, saved one per line as `<user ID> <login>`, so the list can be shown while
+/// they're offline.
+#[derive(Clone, Debug, Default)]
+pub struct BroadcasterList {
+    entries: Vec<(String, String)>,
+}
+
+impl BroadcasterList {
+    pub fn load(account_path: &Path, file_name: &str) -> Self {
+        let entries = std::fs::read_to_string(account_path.join(file_name))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let (user_id, login) = line.trim().split_once(' ')?;
+                Some((user_id.to_string(), login.to_string()))
+            })
+            .collect();
+        Self { entries }
+    }
+
+    pub fn save(&self, account_path: &Path, file_name: &str) -> std::io::Result<()> {
+        let lines: Vec<String> = self
+            .entries
+            .iter()
+            .map(|(user_id, login)| format!("{user_id} {login}"))
+            .collect();
+        std::fs::write(account_path.join(file_name), lines.join("\n"))
+    }
+
+    pub fn contains(&self, user_id: &str) -> bool {
+        self.entries.iter().any(|(id, _)| id == user_id)
+    }
+
+    /// Adds the broadcaster if they're not on the list, and takes them off if they are.
+    pub fn toggle(&mut self, user_id: &str, login: &str) {
+        if self.contains(user_id) {
+            self.remove(user_id);
+        } else {
+            self.entries.push((user_id.to_string(), login.to_string()));
+        }
+    }
+
+    pub fn remove(&mut self, user_id: &str) {
+        self.entries.retain(|(id, _)| id != user_id);
+    }
+
+    /// The `(user ID, login)` of everyone on the list, in the order they were added.
+    pub fn entries(&self) -> &[(String, String)] {
+        &self.entries
+    }
+}
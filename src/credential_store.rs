@@ -0,0 +1,227 @@
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::io::Write;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+
+/// Environment variable holding the passphrase used to encrypt stored credentials.
+pub const PASSPHRASE_ENV: &str = "ICED_TWITCH_GUI_PASSPHRASE";
+const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// Somewhere secrets like access tokens can be kept between runs.
+pub trait CredentialStore {
+    fn read(&self, name: &str) -> Option<String>;
+    fn write(&self, name: &str, value: &str) -> std::io::Result<()>;
+    fn remove(&self, name: &str);
+}
+
+/// Writes `contents` to `path`, readable and writable only by the current user.
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // `mode` only applies to newly created files, so tighten files left over from older versions too.
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    let mut f = options.open(path)?;
+    f.write_all(contents)
+}
+
+/// Stores each credential as a plaintext file with 0600 permissions.
+pub struct FileStore {
+    directory: PathBuf,
+}
+
+impl CredentialStore for FileStore {
+    fn read(&self, name: &str) -> Option<String> {
+        std::fs::read_to_string(self.directory.join(name))
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    }
+    fn write(&self, name: &str, value: &str) -> std::io::Result<()> {
+        write_private(&self.directory.join(name), value.as_bytes())
+    }
+    fn remove(&self, name: &str) {
+        let _ = std::fs::remove_file(self.directory.join(name));
+    }
+}
+
+/// Stores each credential as `<name>.enc`, encrypted with ChaCha20-Poly1305 under a key derived from a passphrase with PBKDF2.
+///
+/// The file layout is `salt || nonce || ciphertext`.
+pub struct EncryptedFileStore {
+    directory: PathBuf,
+    passphrase: String,
+}
+
+impl EncryptedFileStore {
+    fn path(&self, name: &str) -> PathBuf {
+        self.directory.join(format!("{name}.enc"))
+    }
+    fn key(&self, salt: &[u8]) -> LessSafeKey {
+        let mut key_bytes = [0u8; 32];
+        ring::pbkdf2::derive(
+            ring::pbkdf2::PBKDF2_HMAC_SHA256,
+            NonZeroU32::new(PBKDF2_ITERATIONS).unwrap(),
+            salt,
+            self.passphrase.as_bytes(),
+            &mut key_bytes,
+        );
+        LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &key_bytes).unwrap())
+    }
+}
+
+impl CredentialStore for EncryptedFileStore {
+    fn read(&self, name: &str) -> Option<String> {
+        let data = std::fs::read(self.path(name)).ok()?;
+        if data.len() < SALT_LEN + NONCE_LEN {
+            return None;
+        }
+        let (salt, rest) = data.split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+        let mut in_out = ciphertext.to_vec();
+        let plaintext = self.key(salt).open_in_place(nonce, Aad::empty(), &mut in_out).ok()?;
+        String::from_utf8(plaintext.to_vec()).ok()
+    }
+    fn write(&self, name: &str, value: &str) -> std::io::Result<()> {
+        let rng = SystemRandom::new();
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rng.fill(&mut salt).map_err(|_| std::io::Error::other("could not generate a salt"))?;
+        rng.fill(&mut nonce).map_err(|_| std::io::Error::other("could not generate a nonce"))?;
+        let mut in_out = value.as_bytes().to_vec();
+        self.key(&salt)
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut in_out)
+            .map_err(|_| std::io::Error::other("could not encrypt the credential"))?;
+        let mut data = salt.to_vec();
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&in_out);
+        write_private(&self.path(name), &data)
+    }
+    fn remove(&self, name: &str) {
+        let _ = std::fs::remove_file(self.path(name));
+    }
+}
+
+/// Tries each store in order when reading.  Writes go to the first store and clear the others,
+/// so a plaintext copy doesn't linger once encryption is turned on.
+pub struct ChainStore {
+    stores: Vec<Box<dyn CredentialStore>>,
+}
+
+impl ChainStore {
+    pub fn new(stores: Vec<Box<dyn CredentialStore>>) -> Self {
+        Self { stores }
+    }
+    /// Moves a credential found in a fallback store into the first store.
+    pub fn migrate(&self, name: &str) {
+        let Some((first, rest)) = self.stores.split_first() else {
+            return;
+        };
+        if first.read(name).is_some() {
+            return;
+        }
+        if let Some(value) = rest.iter().find_map(|store| store.read(name)) {
+            let _ = self.write(name, &value);
+        }
+    }
+}
+
+impl CredentialStore for ChainStore {
+    fn read(&self, name: &str) -> Option<String> {
+        self.stores.iter().find_map(|store| store.read(name))
+    }
+    fn write(&self, name: &str, value: &str) -> std::io::Result<()> {
+        let Some((first, rest)) = self.stores.split_first() else {
+            return Ok(());
+        };
+        first.write(name, value)?;
+        for store in rest {
+            store.remove(name);
+        }
+        Ok(())
+    }
+    fn remove(&self, name: &str) {
+        for store in &self.stores {
+            store.remove(name);
+        }
+    }
+}
+
+/// The credential store for `directory`: encrypted when a passphrase is set in the environment,
+/// falling back to 0600 plaintext files.
+pub fn for_directory(directory: PathBuf) -> ChainStore {
+    let mut stores: Vec<Box<dyn CredentialStore>> = Vec::new();
+    if let Some(passphrase) = std::env::var(PASSPHRASE_ENV).ok().filter(|p| !p.is_empty()) {
+        stores.push(Box::new(EncryptedFileStore {
+            directory: directory.clone(),
+            passphrase,
+        }));
+    }
+    stores.push(Box::new(FileStore { directory }));
+    ChainStore::new(stores)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encrypted(directory: &Path, passphrase: &str) -> EncryptedFileStore {
+        EncryptedFileStore {
+            directory: directory.to_path_buf(),
+            passphrase: passphrase.to_string(),
+        }
+    }
+
+    #[test]
+    fn encrypted_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = encrypted(dir.path(), "hunter2");
+        store.write("access_token.txt", "secret-token").unwrap();
+        assert_eq!(store.read("access_token.txt").as_deref(), Some("secret-token"));
+        let raw = std::fs::read(dir.path().join("access_token.txt.enc")).unwrap();
+        assert!(!raw.windows(b"secret-token".len()).any(|window| window == b"secret-token"));
+    }
+
+    #[test]
+    fn wrong_passphrase_reads_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        encrypted(dir.path(), "hunter2").write("access_token.txt", "secret-token").unwrap();
+        assert_eq!(encrypted(dir.path(), "wrong").read("access_token.txt"), None);
+    }
+
+    #[test]
+    fn migrate_moves_plaintext_into_encrypted_store() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("refresh_token.txt"), "refresh-me\n").unwrap();
+        let chain = ChainStore::new(vec![
+            Box::new(encrypted(dir.path(), "hunter2")),
+            Box::new(FileStore { directory: dir.path().to_path_buf() }),
+        ]);
+        chain.migrate("refresh_token.txt");
+        assert!(!dir.path().join("refresh_token.txt").exists());
+        assert!(dir.path().join("refresh_token.txt.enc").exists());
+        assert_eq!(chain.read("refresh_token.txt").as_deref(), Some("refresh-me"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_tightens_existing_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("access_token.txt");
+        std::fs::write(&path, "old").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        FileStore { directory: dir.path().to_path_buf() }.write("access_token.txt", "new").unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+    }
+}
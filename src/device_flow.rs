@@ -0,0 +1,135 @@
+use serde::Deserialize;
+use std::time::Duration;
+use twitch_api::twitch_oauth2::Scope;
+
+const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// The code the user has to enter at `verification_uri` to approve this device.
+#[derive(Clone, Debug, Deserialize)]
+pub struct DeviceCode {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub expires_in: u64,
+    pub interval: u64,
+}
+
+/// Tokens handed out by the device code and refresh token grants.
+#[derive(Clone, Debug, Deserialize)]
+pub struct DeviceToken {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+}
+
+/// Why a refresh token couldn't be traded for a new access token.
+#[derive(Clone, Debug)]
+pub enum RefreshError {
+    /// Twitch turned the refresh token down, so the user has to sign in again.
+    Rejected(String),
+    /// Twitch couldn't be reached or failed to answer; the refresh token may still be good.
+    Unreachable(String),
+}
+
+#[derive(Deserialize)]
+struct TokenErrorResponse {
+    message: String,
+}
+
+fn scope_string(scopes: &[Scope]) -> String {
+    scopes
+        .iter()
+        .map(|scope| scope.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+async fn post_token_form(
+    client: &reqwest::Client,
+    oauth2_url: &str,
+    params: &[(&str, &str)],
+) -> Result<Result<DeviceToken, (reqwest::StatusCode, String)>, String> {
+    let response = client
+        .post(format!("{oauth2_url}token"))
+        .form(params)
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach Twitch: {e}"))?;
+    if response.status().is_success() {
+        response
+            .json::<DeviceToken>()
+            .await
+            .map(Ok)
+            .map_err(|e| format!("Could not read the token from Twitch: {e}"))
+    } else {
+        let status = response.status();
+        let message = response
+            .json::<TokenErrorResponse>()
+            .await
+            .map(|error| error.message)
+            .unwrap_or(status.to_string());
+        Ok(Err((status, message)))
+    }
+}
+
+pub async fn request_device_code(client: reqwest::Client, oauth2_url: String, client_id: String, scopes: Vec<Scope>) -> Result<DeviceCode, String> {
+    let scopes = scope_string(&scopes);
+    let response = client
+        .post(format!("{oauth2_url}device"))
+        .form(&[("client_id", client_id.as_str()), ("scopes", scopes.as_str())])
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach Twitch: {e}"))?;
+    if !response.status().is_success() {
+        return Err(format!("Twitch refused the device code request: {}", response.status()));
+    }
+    response
+        .json::<DeviceCode>()
+        .await
+        .map_err(|e| format!("Could not read the device code from Twitch: {e}"))
+}
+
+/// Polls the token endpoint until the user approves or denies the device, or the code expires.
+pub async fn poll_device_token(
+    client: reqwest::Client,
+    oauth2_url: String,
+    client_id: String,
+    scopes: Vec<Scope>,
+    device_code: DeviceCode,
+) -> Result<DeviceToken, String> {
+    let scopes = scope_string(&scopes);
+    let mut interval = Duration::from_secs(device_code.interval.max(1));
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(device_code.expires_in);
+    while tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(interval).await;
+        let params = [
+            ("client_id", client_id.as_str()),
+            ("scopes", scopes.as_str()),
+            ("device_code", device_code.device_code.as_str()),
+            ("grant_type", DEVICE_CODE_GRANT_TYPE),
+        ];
+        match post_token_form(&client, &oauth2_url, &params).await? {
+            Ok(token) => return Ok(token),
+            Err((_, message)) if message == "authorization_pending" => {}
+            Err((_, message)) if message == "slow_down" => interval += Duration::from_secs(5),
+            Err((_, message)) => return Err(format!("Device sign-in failed: {message}")),
+        }
+    }
+    Err(String::from("The device code expired before it was approved."))
+}
+
+/// Trades a refresh token for a new access token.  Public clients don't send a client secret.
+pub async fn refresh_access_token(client: reqwest::Client, oauth2_url: String, client_id: String, refresh_token: String) -> Result<DeviceToken, RefreshError> {
+    let params = [
+        ("client_id", client_id.as_str()),
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token.as_str()),
+    ];
+    match post_token_form(&client, &oauth2_url, &params).await {
+        Ok(Ok(token)) => Ok(token),
+        Ok(Err((status, message))) if status == reqwest::StatusCode::BAD_REQUEST || status == reqwest::StatusCode::UNAUTHORIZED => {
+            Err(RefreshError::Rejected(format!("Could not refresh the access token: {message}")))
+        }
+        Ok(Err((_, message))) => Err(RefreshError::Unreachable(format!("Could not refresh the access token: {message}"))),
+        Err(error) => Err(RefreshError::Unreachable(error)),
+    }
+}
@@ -0,0 +1,124 @@
+use std::fs::FileTimes;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+pub const DEFAULT_PREVIEW_TTL: Duration = Duration::from_secs(5 * 60);
+pub const DEFAULT_MAX_BYTES: u64 = 100 * 1024 * 1024;
+/// Box art and avatars hardly ever change, unlike stream previews.
+pub const STATIC_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Whether `data` starts like a JPEG, PNG, GIF or WebP file.
+fn is_image(data: &[u8]) -> bool {
+    data.starts_with(&[0xff, 0xd8, 0xff])
+        || data.starts_with(b"\x89PNG")
+        || data.starts_with(b"GIF8")
+        || (data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP"))
+}
+
+/// Images downloaded from Twitch, stored on disk under a hash of their URL.
+///
+/// A file's modification time is when it was downloaded, and its access time is when it was last used,
+/// which is what least-recently-used eviction goes by.
+#[derive(Clone, Debug)]
+pub struct ImageCache {
+    pub client: reqwest::Client,
+    directory: PathBuf,
+    pub preview_ttl: Duration,
+    pub max_bytes: u64,
+}
+
+impl ImageCache {
+    pub fn new(client: reqwest::Client, directory: PathBuf, preview_ttl: Duration, max_bytes: u64) -> Self {
+        Self {
+            client,
+            directory,
+            preview_ttl,
+            max_bytes,
+        }
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        let digest = ring::digest::digest(&ring::digest::SHA256, url.as_bytes());
+        let name: String = digest.as_ref().iter().map(|b| format!("{b:02x}")).collect();
+        self.directory.join(name)
+    }
+
+    /// The cached image for `url`, if it was downloaded less than `ttl` ago.
+    pub fn get(&self, url: &str, ttl: Duration) -> Option<Vec<u8>> {
+        let path = self.path(url);
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+        if SystemTime::now().duration_since(modified).unwrap_or_default() > ttl {
+            return None;
+        }
+        let data = std::fs::read(&path).ok()?;
+        if let Ok(f) = std::fs::File::options().write(true).open(&path) {
+            let _ = f.set_times(FileTimes::new().set_accessed(SystemTime::now()));
+        }
+        Some(data)
+    }
+
+    pub fn put(&self, url: &str, data: &[u8]) {
+        if std::fs::create_dir_all(&self.directory).is_err() {
+            return;
+        }
+        if std::fs::write(self.path(url), data).is_ok() {
+            self.evict();
+        }
+    }
+
+    /// Deletes the least recently used images until the cache fits in `max_bytes`.
+    fn evict(&self) {
+        let Ok(entries) = std::fs::read_dir(&self.directory) else {
+            return;
+        };
+        let mut files: Vec<(PathBuf, u64, SystemTime)> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                let used = metadata.accessed().or(metadata.modified()).ok()?;
+                metadata.is_file().then(|| (entry.path(), metadata.len(), used))
+            })
+            .collect();
+        let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+        files.sort_by_key(|(_, _, used)| *used);
+        for (path, len, _) in files {
+            if total <= self.max_bytes {
+                break;
+            }
+            if std::fs::remove_file(path).is_ok() {
+                total -= len;
+            }
+        }
+    }
+
+    async fn download(&self, url: &str) -> Result<Vec<u8>, String> {
+        let req = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .and_then(|req| req.error_for_status())
+            .map_err(|e| e.to_string())?;
+        let data = req.bytes().await.map_err(|e| e.to_string())?.to_vec();
+        if !is_image(&data) {
+            return Err(String::from("not an image"));
+        }
+        Ok(data)
+    }
+
+    /// Gets `url` from the cache if it's younger than `ttl`, downloading and caching it otherwise.
+    ///
+    /// If the download fails, an older copy is better than nothing, so that's used if there is one.
+    pub async fn fetch(&self, url: &str, ttl: Duration) -> Result<Vec<u8>, String> {
+        if let Some(data) = self.get(url, ttl).filter(|data| is_image(data)) {
+            return Ok(data);
+        }
+        match self.download(url).await {
+            Ok(data) => {
+                self.put(url, &data);
+                Ok(data)
+            }
+            Err(e) => self.get(url, Duration::MAX).filter(|data| is_image(data)).ok_or(e),
+        }
+    }
+}
@@ -1,27 +1,198 @@
-use futures::TryStreamExt;
+mod broadcaster_list;
+mod credential_store;
+mod device_flow;
+mod image_cache;
+mod network;
+mod notifications;
+mod redirect_server;
+mod scheduler;
+mod scopes;
+mod search;
+mod settings;
+mod snapshot;
+mod sort;
+mod twitch_config;
+
+use broadcaster_list::BroadcasterList;
+use credential_store::CredentialStore;
+use device_flow::{DeviceCode, DeviceToken, RefreshError};
+use redirect_server::RedirectError;
+use scheduler::ScheduledClient;
+use scopes::Feature;
+use snapshot::Snapshot;
+use sort::SortOrder;
+use twitch_config::TwitchConfig;
+use futures::{StreamExt, TryStreamExt};
+use image_cache::ImageCache;
+use network::NetworkConfig;
+use notifications::Urgency;
 use iced::time::{self, Duration, Instant};
 use home::home_dir;
 use iced::alignment::Vertical::Top;
 use iced::event::{self, Event};
-use iced::widget::{button, column, container, image, row, scrollable, text, text_input, Space};
+use iced::keyboard;
+use iced::widget::{
+    button, center, checkbox, column, container, image, mouse_area, opaque, pick_list, row, scrollable, stack, text, text_input, Space,
+};
 use iced::{
-    window, Bottom, Center, Element, Fill, FillPortion, Padding, Renderer, Shrink, Subscription,
+    mouse, window, Bottom, Center, Color, Element, Fill, FillPortion, Padding, Renderer, Shrink, Subscription,
     Task,
 };
-use std::io::{BufRead, BufReader, Write};
-use std::net::TcpListener;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::SystemTime;
+use std::io::Write;
 //use std::path::PathBuf;
 use std::process::Stdio;
-use twitch_api::helix::{streams::Stream, HelixClient};
-use twitch_api::twitch_oauth2::{tokens, types::ClientId, AccessToken, Scope, UserToken};
-use url::Url;
-const CLIENT_ID: &str = "reh9rt391dkrperi4b7cqelryifsej";
+use twitch_api::helix::{streams::Stream, ClientRequestError, HelixClient, HelixRequestGetError};
+use twitch_api::types::{CategoryId, UserId};
+use twitch_api::twitch_oauth2::tokens::errors::ValidationError;
+use twitch_api::twitch_oauth2::{
+    tokens, types::ClientId, AccessToken, Scope, TwitchToken, UserToken,
+};
+const DEFAULT_ACCOUNT: &str = "default";
+const DEFAULT_THUMBNAIL_CONCURRENCY: usize = 8;
+/// Shown in place of thumbnails that are still loading or failed to load.
+static PLACEHOLDER_IMAGE: LazyLock<image::Handle> =
+    LazyLock::new(|| image::Handle::from_bytes(include_bytes!("../icon.png").as_slice()));
+const MAX_THUMBNAIL_RETRIES: u32 = 3;
+const SEARCH_INPUT: &str = "search";
+const TOKEN_EXPIRING_SOON: Duration = Duration::from_secs(24 * 60 * 60);
+/// Refreshable tokens only live a few hours, so they're renewed once less than this is left.  It's longer than the
+/// hourly validation so the token can't run out between two checks.
+const REFRESH_BEFORE_EXPIRY: Duration = Duration::from_secs(90 * 60);
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum AuthStatus {
+    Unknown,
+    Valid,
+    ExpiringSoon,
+    Invalid,
+}
+impl AuthStatus {
+    fn from_expires_in(expires_in: Option<Duration>, refreshable: bool) -> Self {
+        let threshold = if refreshable { REFRESH_BEFORE_EXPIRY } else { TOKEN_EXPIRING_SOON };
+        match expires_in {
+            Some(expires_in) if expires_in < threshold => AuthStatus::ExpiringSoon,
+            _ => AuthStatus::Valid,
+        }
+    }
+    fn label(&self) -> &'static str {
+        match self {
+            AuthStatus::Unknown => "Signing in...",
+            AuthStatus::Valid => "Signed in",
+            AuthStatus::ExpiringSoon => "Sign-in expiring soon",
+            AuthStatus::Invalid => "Sign-in invalid",
+        }
+    }
+}
+/// Why a saved access token couldn't be used.
+#[derive(Clone, Debug)]
+enum TokenError {
+    /// Twitch turned it down, so the user has to sign in again.
+    Rejected(String),
+    /// Twitch couldn't be reached, so the token may still be good.
+    Unreachable(String),
+}
+/// Why fetching the followed streams failed.
+#[derive(Clone, Debug)]
+enum FetchError {
+    /// Twitch couldn't be reached or is having trouble, so try again later.
+    Unreachable(String),
+    /// The access token has expired or was revoked.
+    Unauthorized(String),
+    /// Twitch turned the request down for some other reason.
+    Failed(String),
+}
+#[derive(Clone, Debug)]
+enum ImageState {
+    Loading,
+    Loaded(image::Handle),
+    Failed { error: String, attempts: u32 },
+}
+/// Parses an RFC 3339 timestamp from Helix.
+fn parse_timestamp(timestamp: &str) -> Option<SystemTime> {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(SystemTime::from)
+}
+/// `1234` as `1,234`, and anything from ten thousand up as `12.3K` or `1.2M`.
+fn viewer_count_label(viewer_count: usize) -> String {
+    if viewer_count < 1000 {
+        viewer_count.to_string()
+    } else if viewer_count < 10_000 {
+        format!("{},{:03}", viewer_count / 1000, viewer_count % 1000)
+    } else if viewer_count < 1_000_000 {
+        format!("{}.{}K", viewer_count / 1000, viewer_count % 1000 / 100)
+    } else {
+        format!("{}.{}M", viewer_count / 1_000_000, viewer_count % 1_000_000 / 100_000)
+    }
+}
+/// How long a stream that started at `started_at` has been live at `now`, like `2h 05m`.
+fn uptime_label(started_at: &str, now: SystemTime) -> String {
+    let Some(started_at) = parse_timestamp(started_at) else {
+        return String::new();
+    };
+    let minutes = (now.duration_since(started_at).unwrap_or_default().as_secs() / 60) as i64;
+    if minutes < 60 {
+        format!("{minutes}m")
+    } else {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    }
+}
+/// A live stream from a followed broadcaster, keyed by the broadcaster's user ID.
+#[derive(Clone, Debug)]
+struct FollowedStream {
+    stream: Stream,
+    thumbnail: ImageState,
+}
+/// A followed channel on the All follows tab.  The avatar and last broadcast are filled in as they arrive.
+#[derive(Clone, Debug)]
+struct FollowedChannel {
+    user_id: String,
+    login: String,
+    display_name: String,
+    game_name: String,
+    avatar_url: String,
+    avatar: Option<image::Handle>,
+    last_broadcast: Option<SystemTime>,
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Tab {
+    Live,
+    AllFollows,
+}
 #[derive(Clone)]
 struct IcedTwitchGui {
-    followed_streams: Vec<Stream>,
-    client: HelixClient<'static, reqwest::Client>,
+    followed_streams: HashMap<String, FollowedStream>,
+    stream_order: Vec<String>,
+    sort_order: SortOrder,
+    search_input: String,
+    group_by_game: bool,
+    /// The IDs of the games whose sections are collapsed.
+    collapsed_games: Vec<String>,
+    /// Box art by game ID.
+    box_art: HashMap<String, image::Handle>,
+    /// The user IDs of the current account's favorite broadcasters.
+    favorites: Vec<String>,
+    favorite_urgency: Urgency,
+    favorite_urgency_input: Urgency,
+    /// Broadcasters left out of the grid.
+    hidden: BroadcasterList,
+    /// Broadcasters who don't get notifications.
+    muted: BroadcasterList,
+    broadcaster_lists_open: bool,
+    tab: Tab,
+    /// Everyone the user follows, by display name, once the All follows tab has been opened.
+    followed_channels: Vec<FollowedChannel>,
+    followed_channels_loading: bool,
+    /// The user ID of the stream in the detail panel, if it's open.
+    details: Option<String>,
+    details_thumbnail: ImageState,
+    /// Channel descriptions by user ID, fetched when the detail panel first opens for a channel.
+    channel_descriptions: HashMap<String, Result<String, String>>,
+    client: HelixClient<'static, ScheduledClient>,
+    http_client: reqwest::Client,
     token: Option<UserToken>,
-    image_handles: Vec<image::Handle>,
     num_columns: usize,
     settings_open: bool,
     stream_command: String,
@@ -31,38 +202,144 @@ struct IcedTwitchGui {
     player_command_input: String,
     twitch_oauth_token_input: String,
     cache_path: std::path::PathBuf,
-    currently_streaming_broadcasters: Vec<String>
+    currently_streaming_broadcasters: Vec<String>,
+    login_required: bool,
+    login_in_progress: bool,
+    /// The sign-in in progress only adds scopes, so the current session stays usable while it runs.
+    granting_permissions: bool,
+    login_error: Option<String>,
+    auth_status: AuthStatus,
+    refresh_token: Option<String>,
+    device_code: Option<DeviceCode>,
+    accounts: Vec<String>,
+    current_account: String,
+    new_account_input: String,
+    redirect_port: u16,
+    redirect_port_input: String,
+    twitch_config: TwitchConfig,
+    helix_url_input: String,
+    oauth2_url_input: String,
+    client_id_input: String,
+    proxy_url_input: String,
+    ca_bundle_path_input: String,
+    missing_features: Vec<Feature>,
+    fetch_error: Option<String>,
+    offline: bool,
+    last_updated: Option<SystemTime>,
+    /// What uptimes are counted up to, moved on every minute.
+    now: SystemTime,
+    refresh_in_flight: bool,
+    /// Goes up whenever the account changes, so that results fetched for the previous one can be told apart and dropped.
+    account_generation: u64,
+    thumbnail_concurrency: usize,
+    thumbnail_concurrency_input: String,
+    image_cache: ImageCache,
+    image_cache_ttl_input: String,
+    image_cache_size_input: String,
 }
 
 #[derive(Clone, Debug)]
 enum Message {
     Refresh,
-    ClickedStream(usize),
-    SaveRefresh((Vec<Stream>, Vec<image::Handle>)),
+    ClickedStream(String),
+    SaveRefresh(u64, Vec<Stream>),
+    GotThumbnail(String, Result<image::Handle, String>),
+    RetryThumbnail(String),
     Startup(String),
-    GotUserToken(UserToken),
+    GotUserToken(u64, Result<UserToken, TokenError>),
+    StartLogin,
+    GotAccessToken(u64, Result<String, RedirectError>),
+    StartDeviceLogin,
+    GotDeviceCode(u64, Result<DeviceCode, String>),
+    OpenVerificationUri,
+    GotDeviceToken(u64, Result<DeviceToken, String>),
+    GotRefreshedToken(u64, Result<DeviceToken, RefreshError>),
+    SwitchAccount(String),
+    NewAccountTextInputChanged(String),
+    AddAccount,
+    GrantPermissions,
+    DismissPermissionPrompt,
+    SignOut,
+    SignedOut(Result<(), String>),
     GotChildProcessId(Option<u32>),
-    OpenChat(usize),
-    OpenChannel(usize),
+    OpenChat(String),
+    OpenChannel(String),
     EventOccurred(Event),
     SettingsToggle,
     ApplySettings,
     PlayerCommandTextInputChanged(String),
     StreamCommandTextInputChanged(String),
     OAuthTokenTextInputChanged(String),
+    RedirectPortTextInputChanged(String),
+    HelixUrlTextInputChanged(String),
+    OAuth2UrlTextInputChanged(String),
+    ClientIdTextInputChanged(String),
+    SortOrderSelected(SortOrder),
+    SearchTextInputChanged(String),
+    GroupByGameToggled(bool),
+    ToggleGameSection(String),
+    GotBoxArt(Vec<(String, image::Handle)>),
+    ToggleFavorite(String),
+    FavoriteUrgencySelected(Urgency),
+    ToggleHidden(String),
+    ToggleMuted(String),
+    BroadcasterListsToggle,
+    SelectTab(Tab),
+    GotFollowedChannels(u64, Result<Vec<FollowedChannel>, String>),
+    GotChannelDetails(String, Option<image::Handle>, Option<SystemTime>),
+    OpenDetails(String),
+    CloseDetails,
+    GotDetailsThumbnail(String, Result<image::Handle, String>),
+    GotChannelDescription(String, Result<String, String>),
+    PlayTopMatch,
+    ProxyUrlTextInputChanged(String),
+    CaBundlePathTextInputChanged(String),
+    ThumbnailConcurrencyTextInputChanged(String),
+    ImageCacheTtlTextInputChanged(String),
+    ImageCacheSizeTextInputChanged(String),
     OneMinute(Instant),
-    CheckAndNotifyNewStreams(Vec<Stream>)
+    OneHour(Instant),
+    TokenValidated(u64, Result<AuthStatus, String>),
+    CheckAndNotifyNewStreams(u64, Vec<Stream>),
+    RefreshFailed(u64, FetchError),
+    FetchFailed(u64, FetchError),
+    DismissError,
+}
+/// Turns the result of a fetch for account `generation` into `on_ok`'s message, or `on_err`'s.
+fn fetch_result<T>(
+    generation: u64,
+    on_ok: fn(u64, T) -> Message,
+    on_err: fn(u64, FetchError) -> Message,
+) -> impl Fn(Result<T, FetchError>) -> Message {
+    move |result| match result {
+        Ok(value) => on_ok(generation, value),
+        Err(error) => on_err(generation, error),
+    }
 }
 async fn get_followed_streams(
-    client: HelixClient<'static, reqwest::Client>,
+    client: HelixClient<'static, ScheduledClient>,
     token: UserToken,
-) -> Vec<Stream> {
-    let followed_streams: Vec<twitch_api::helix::streams::Stream> = client
+) -> Result<Vec<Stream>, FetchError> {
+    client
         .get_followed_streams(&token)
         .try_collect()
         .await
-        .unwrap();
-    followed_streams
+        .map_err(|e| {
+            let message = format!("Could not get your followed streams: {e}");
+            match e {
+                ClientRequestError::RequestError(_) => FetchError::Unreachable(message),
+                ClientRequestError::HelixRequestGetError(HelixRequestGetError::Error { status, .. }) => {
+                    if status == http::StatusCode::UNAUTHORIZED {
+                        FetchError::Unauthorized(message)
+                    } else if status.is_server_error() {
+                        FetchError::Unreachable(message)
+                    } else {
+                        FetchError::Failed(message)
+                    }
+                }
+                _ => FetchError::Failed(message),
+            }
+        })
 }
 async fn start_streaming(stream_command: String, player_command: String, oauth_token: String, title: String, broadcaster_displayname: String, broadcaster_username: String) -> Option<u32> {
     let new_stream_command_parts: Vec<String> = serde_cmd::ArgIter::new(stream_command.as_str()).map(|x| x.replace("$title", &title).replace("$oauth_token", &oauth_token).replace("$broadcaster_username", &broadcaster_username).replace("$broadcaster_displayname", &broadcaster_displayname).replace("\"", "").to_string()).collect();
@@ -82,93 +359,667 @@ async fn start_streaming(stream_command: String, player_command: String, oauth_t
         tokio::process::Command::new(stream_program).args(stream_args).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::inherit()).spawn().unwrap().id()
     }
 }
-async fn get_thumnails(streams: Vec<Stream>) -> Vec<image::Handle> {
-    let mut image_handle_vec = Vec::new();
-    for stream in streams {
-        let req = reqwest::get(
-            stream
-                .thumbnail_url
-                .replace("{width}", "320")
-                .replace("{height}", "180"),
-        )
+fn thumbnail_url(stream: &Stream, width: u32, height: u32) -> String {
+    stream
+        .thumbnail_url
+        .replace("{width}", &width.to_string())
+        .replace("{height}", &height.to_string())
+}
+/// The stream's thumbnail at `width` x `height`: 320x180 for the cards, 640x360 for the detail panel.
+async fn get_thumnail(image_cache: ImageCache, stream: Stream, width: u32, height: u32) -> (String, Result<image::Handle, String>) {
+    let url = thumbnail_url(&stream, width, height);
+    let result = image_cache
+        .fetch(&url, image_cache.preview_ttl)
         .await
-        .unwrap();
-        let data = req.bytes().await.unwrap();
-        image_handle_vec.push(image::Handle::from_bytes(data));
-    }
-    image_handle_vec
+        .map(image::Handle::from_bytes)
+    .map_err(|e| format!("Could not get the thumbnail for {}: {e}", stream.user_login));
+    (stream.user_id.to_string(), result)
+}
+async fn get_channel_description(
+    client: HelixClient<'static, ScheduledClient>,
+    token: UserToken,
+    user_id: String,
+) -> (String, Result<String, String>) {
+    let result = client
+        .get_user_from_id(user_id.as_str(), &token)
+        .await
+        .map(|user| user.and_then(|user| user.description).unwrap_or_default())
+        .map_err(|e| format!("Could not get the channel description: {e}"));
+    (user_id, result)
+}
+/// Tries a failed thumbnail again after a delay that doubles with each attempt.
+fn retry_thumnail(image_cache: ImageCache, stream: Stream, attempts: u32) -> Task<Message> {
+    Task::perform(
+        async move {
+            tokio::time::sleep(Duration::from_secs(5 * 2u64.pow(attempts))).await;
+            get_thumnail(image_cache, stream, 320, 180).await
+        },
+        |(user_id, result)| Message::GotThumbnail(user_id, result),
+    )
+}
+/// Downloads the thumbnails of `streams`, at most `concurrency` at a time, sending each one as soon as it arrives.
+fn get_thumnails(image_cache: ImageCache, streams: Vec<Stream>, concurrency: usize) -> Task<Message> {
+    Task::run(
+        futures::stream::iter(streams)
+            .map(move |stream| get_thumnail(image_cache.clone(), stream, 320, 180))
+            .buffer_unordered(concurrency.max(1)),
+        |(user_id, result)| Message::GotThumbnail(user_id, result),
+    )
+}
+/// Looks up the box art of `game_ids` and downloads it.  Box art is just decoration, so games without any are left out.
+async fn get_box_art(
+    client: HelixClient<'static, ScheduledClient>,
+    token: UserToken,
+    image_cache: ImageCache,
+    game_ids: Vec<CategoryId>,
+) -> Vec<(String, image::Handle)> {
+    let ids = game_ids.into();
+    let games: Vec<_> = client
+        .get_games_by_id(&ids, &token)
+        .filter_map(|game| async move { game.ok() })
+        .collect()
+        .await;
+    futures::stream::iter(games)
+        .map(|game| {
+            let image_cache = image_cache.clone();
+            async move {
+                let url = game.box_art_url.replace("{width}", "52").replace("{height}", "72");
+                let data = image_cache.fetch(&url, image_cache::STATIC_TTL).await.ok()?;
+                Some((game.id.to_string(), image::Handle::from_bytes(data)))
+            }
+        })
+        .buffer_unordered(DEFAULT_THUMBNAIL_CONCURRENCY)
+        .filter_map(|box_art| async move { box_art })
+        .collect()
+        .await
+}
+/// Everyone `token`'s user follows, with the game they played last and where to get their avatar.
+async fn get_followed_channels(
+    client: HelixClient<'static, ScheduledClient>,
+    token: UserToken,
+) -> Result<Vec<FollowedChannel>, String> {
+    let follows: Vec<_> = client
+        .get_followed_channels(&token.user_id, &token)
+        .try_collect()
+        .await
+        .map_err(|e| format!("Could not get the channels you follow: {e}"))?;
+    let ids: Vec<UserId> = follows.iter().map(|follow| follow.broadcaster_id.clone()).collect();
+    let ids = ids.into();
+    let channels: Vec<_> = client
+        .get_channels_from_ids(&ids, &token)
+        .try_collect()
+        .await
+        .map_err(|e| format!("Could not get the channels you follow: {e}"))?;
+    let users: Vec<_> = client
+        .get_users_from_ids(&ids, &token)
+        .try_collect()
+        .await
+        .map_err(|e| format!("Could not get the channels you follow: {e}"))?;
+    let mut followed_channels: Vec<FollowedChannel> = follows
+        .into_iter()
+        .map(|follow| FollowedChannel {
+            game_name: channels
+                .iter()
+                .find(|channel| channel.broadcaster_id == follow.broadcaster_id)
+                .map(|channel| channel.game_name.to_string())
+                .unwrap_or_default(),
+            avatar_url: users
+                .iter()
+                .find(|user| user.id == follow.broadcaster_id)
+                .and_then(|user| user.profile_image_url.clone())
+                .unwrap_or_default(),
+            user_id: follow.broadcaster_id.to_string(),
+            login: follow.broadcaster_login.to_string(),
+            display_name: follow.broadcaster_name.to_string(),
+            avatar: None,
+            last_broadcast: None,
+        })
+        .collect();
+    followed_channels.sort_by_key(|channel| channel.display_name.to_lowercase());
+    Ok(followed_channels)
+}
+/// When `user_id` last went live, going by their most recent past broadcast.  Channels that don't keep
+/// past broadcasts don't have one.
+async fn get_last_broadcast(
+    client: HelixClient<'static, ScheduledClient>,
+    token: UserToken,
+    user_id: String,
+) -> Option<SystemTime> {
+    let mut request = twitch_api::helix::videos::GetVideosRequest::user_id(user_id.as_str());
+    request.type_ = Some(twitch_api::helix::videos::VideoTypeFilter::Archive);
+    request.first = Some(1);
+    let response = client.req_get(request, &token).await.ok()?;
+    let video = response.data.into_iter().next()?;
+    parse_timestamp(video.created_at.as_str())
 }
-async fn fetch_followed_streams_get_thumnails(
-    client: HelixClient<'static, reqwest::Client>,
-    user_token: UserToken,
-) -> (Vec<Stream>, Vec<image::Handle>) {
-    let streams = get_followed_streams(client.clone(), user_token.clone()).await;
-    (streams.clone(), get_thumnails(streams.clone()).await)
+/// Downloads the avatars of `channels` and looks up their last broadcasts, sending each channel's as soon as it arrives.
+fn get_channel_details(
+    client: HelixClient<'static, ScheduledClient>,
+    token: UserToken,
+    image_cache: ImageCache,
+    channels: Vec<FollowedChannel>,
+    concurrency: usize,
+) -> Task<Message> {
+    Task::run(
+        futures::stream::iter(channels)
+            .map(move |channel| {
+                let (client, token, image_cache) = (client.clone(), token.clone(), image_cache.clone());
+                async move {
+                    let avatar = match channel.avatar_url.as_str() {
+                        _ if channel.avatar.is_some() => None,
+                        "" => None,
+                        url => image_cache.fetch(url, image_cache::STATIC_TTL).await.ok().map(image::Handle::from_bytes),
+                    };
+                    let last_broadcast = match channel.last_broadcast {
+                        Some(_) => None,
+                        None => get_last_broadcast(client, token, channel.user_id.clone()).await,
+                    };
+                    (channel.user_id, avatar, last_broadcast)
+                }
+            })
+            .buffer_unordered(concurrency.max(1)),
+        |(user_id, avatar, last_broadcast)| Message::GotChannelDetails(user_id, avatar, last_broadcast),
+    )
 }
 async fn get_user_token(
-    client: HelixClient<'static, reqwest::Client>,
+    client: HelixClient<'static, ScheduledClient>,
     access_token_string: String,
-) -> UserToken {
-    let token = UserToken::from_token(&client, AccessToken::from(access_token_string.as_str()))
+) -> Result<UserToken, TokenError> {
+    UserToken::from_token(&client, AccessToken::from(access_token_string.trim()))
         .await
-        .unwrap();
+        .map_err(|e| match e {
+            ValidationError::NotAuthorized => {
+                TokenError::Rejected(String::from("The saved access token has expired or was revoked."))
+            }
+            ValidationError::Request(e) => {
+                TokenError::Unreachable(format!("Could not reach Twitch to validate the saved access token: {e}"))
+            }
+            e => TokenError::Rejected(format!("Could not validate the saved access token: {e}")),
+        })
+}
+async fn validate_user_token(
+    client: HelixClient<'static, ScheduledClient>,
+    token: UserToken,
+    refreshable: bool,
+) -> Result<AuthStatus, String> {
+    match token.validate_token(&client).await {
+        Ok(validated) => Ok(AuthStatus::from_expires_in(validated.expires_in, refreshable)),
+        Err(ValidationError::NotAuthorized) => Ok(AuthStatus::Invalid),
+        Err(e) => Err(format!("Could not validate the access token: {e}")),
+    }
+}
+async fn revoke_user_token(
+    client: HelixClient<'static, ScheduledClient>,
+    token: UserToken,
+) -> Result<(), String> {
     token
+        .revoke_token(&client)
+        .await
+        .map_err(|e| format!("Could not revoke the access token: {e}"))
+}
+/// Signs in through the browser.  With `force_verify`, Twitch asks which user to sign in as instead of silently
+/// approving whoever is signed in to Twitch in the browser.
+async fn redirect_login(client_id: String, port: u16, scopes: Vec<Scope>, force_verify: bool) -> Result<String, RedirectError> {
+    let mut token_builder = tokens::ImplicitUserTokenBuilder::new(
+        ClientId::new(client_id),
+        format!("http://localhost:{port}/redirect").parse().unwrap(),
+    )
+    .set_scopes(scopes)
+    .force_verify(force_verify);
+    let listener = redirect_server::bind(port).await?;
+    let (url, csrf) = token_builder.generate_url();
+    webbrowser::open(url.as_str()).map_err(|e| RedirectError::Browser(e.to_string()))?;
+    redirect_server::wait_for_token(listener, csrf.secret().to_string(), redirect_server::DEFAULT_TIMEOUT).await
 }
 impl IcedTwitchGui {
-    fn new() -> Self {
-        let client: HelixClient<reqwest::Client> = HelixClient::new();
+    fn new(twitch_config: TwitchConfig, network_config: NetworkConfig) -> Self {
+        // Without the proxy or CA, requests will most likely fail, but that shows up on the next fetch.
+        let (http_client, network_error) = match network_config.client() {
+            Ok(http_client) => (http_client, None),
+            Err(error) => (NetworkConfig::default().client().unwrap(), Some(error)),
+        };
+        let client: HelixClient<ScheduledClient> =
+            HelixClient::with_client(ScheduledClient::new(http_client.clone()));
         let settings_path = home_dir().unwrap().join(".cache").join("iced_twitch_gui");
         let stream_command = std::fs::read_to_string(settings_path.join("stream_command.txt"))
             .unwrap_or(String::from("twitch-hls-client $broadcaster_username"));
         let player_command = std::fs::read_to_string(settings_path.join("player_command.txt"))
-            .unwrap_or(String::new());
-        let oauth_token =
-            std::fs::read_to_string(settings_path.join("oauth_token.txt")).unwrap_or(String::new());
+            .unwrap_or_default();
+        let redirect_port = std::fs::read_to_string(settings_path.join("redirect_port.txt"))
+            .ok()
+            .and_then(|port| port.trim().parse().ok())
+            .unwrap_or(redirect_server::DEFAULT_PORT);
+        let thumbnail_concurrency = std::fs::read_to_string(settings_path.join("thumbnail_concurrency.txt"))
+            .ok()
+            .and_then(|concurrency| concurrency.trim().parse().ok())
+            .filter(|concurrency| *concurrency > 0)
+            .unwrap_or(DEFAULT_THUMBNAIL_CONCURRENCY);
+        let image_cache_ttl = std::fs::read_to_string(settings_path.join("image_cache_ttl_minutes.txt"))
+            .ok()
+            .and_then(|minutes| minutes.trim().parse().ok())
+            .map(|minutes: u64| Duration::from_secs(minutes * 60))
+            .unwrap_or(image_cache::DEFAULT_PREVIEW_TTL);
+        let image_cache_size = std::fs::read_to_string(settings_path.join("image_cache_size_mb.txt"))
+            .ok()
+            .and_then(|mb| mb.trim().parse().ok())
+            .map(|mb: u64| mb * 1024 * 1024)
+            .unwrap_or(image_cache::DEFAULT_MAX_BYTES);
+        let favorite_urgency = Urgency::load(&settings_path, "favorite_urgency.txt").unwrap_or(Urgency::Critical);
+        let image_cache = ImageCache::new(http_client.clone(), settings_path.join("images"), image_cache_ttl, image_cache_size);
+        let mut accounts = vec![String::from(DEFAULT_ACCOUNT)];
+        if let Ok(entries) = std::fs::read_dir(settings_path.join("accounts")) {
+            let mut named_accounts: Vec<String> = entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|name| name != DEFAULT_ACCOUNT)
+                .collect();
+            named_accounts.sort();
+            accounts.extend(named_accounts);
+        }
+        let current_account = std::fs::read_to_string(settings_path.join("current_account.txt"))
+            .map(|account| account.trim().to_string())
+            .ok()
+            .filter(|account| accounts.contains(account))
+            .unwrap_or(String::from(DEFAULT_ACCOUNT));
         Self {
-            followed_streams: Vec::new(),
-            client: client,
+            followed_streams: HashMap::new(),
+            stream_order: Vec::new(),
+            sort_order: SortOrder::load(&settings_path),
+            search_input: String::new(),
+            group_by_game: std::fs::read_to_string(settings_path.join("group_by_game.txt"))
+                .map(|group_by_game| group_by_game.trim() == "true")
+                .unwrap_or_default(),
+            collapsed_games: std::fs::read_to_string(settings_path.join("collapsed_games.txt"))
+                .unwrap_or_default()
+                .lines()
+                .map(String::from)
+                .collect(),
+            box_art: HashMap::new(),
+            favorites: Vec::new(),
+            favorite_urgency,
+            favorite_urgency_input: favorite_urgency,
+            hidden: BroadcasterList::default(),
+            muted: BroadcasterList::default(),
+            broadcaster_lists_open: false,
+            tab: Tab::Live,
+            followed_channels: Vec::new(),
+            followed_channels_loading: false,
+            details: None,
+            details_thumbnail: ImageState::Loading,
+            channel_descriptions: HashMap::new(),
+            client,
+            http_client,
             token: None,
-            image_handles: Vec::new(),
             num_columns: 4,
             settings_open: false,
             stream_command: stream_command.clone(),
             player_command: player_command.clone(),
-            twitch_oauth_token: oauth_token.clone(),
+            twitch_oauth_token: String::new(),
             stream_command_input: stream_command.clone(),
             player_command_input: player_command.clone(),
-            twitch_oauth_token_input: oauth_token.clone(),
+            twitch_oauth_token_input: String::new(),
             cache_path: home_dir().unwrap().join(".cache").join("iced_twitch_gui"),
-            currently_streaming_broadcasters: Vec::new()
+            currently_streaming_broadcasters: Vec::new(),
+            login_required: false,
+            login_in_progress: false,
+            granting_permissions: false,
+            login_error: None,
+            auth_status: AuthStatus::Unknown,
+            refresh_token: None,
+            device_code: None,
+            accounts,
+            current_account,
+            new_account_input: String::new(),
+            redirect_port,
+            redirect_port_input: redirect_port.to_string(),
+            helix_url_input: twitch_config.helix_url.clone(),
+            oauth2_url_input: twitch_config.oauth2_url.clone(),
+            client_id_input: twitch_config.client_id.clone(),
+            twitch_config,
+            proxy_url_input: network_config.proxy_url,
+            ca_bundle_path_input: network_config.ca_bundle_path,
+            missing_features: Vec::new(),
+            fetch_error: network_error,
+            offline: false,
+            last_updated: None,
+            now: SystemTime::now(),
+            refresh_in_flight: false,
+            account_generation: 0,
+            thumbnail_concurrency,
+            thumbnail_concurrency_input: thumbnail_concurrency.to_string(),
+            image_cache_ttl_input: (image_cache.preview_ttl.as_secs() / 60).to_string(),
+            image_cache_size_input: (image_cache.max_bytes / 1024 / 1024).to_string(),
+            image_cache,
+        }
+    }
+    /// The default account keeps its files directly in the cache directory, other accounts get a subdirectory.
+    fn account_dir(&self, account: &str) -> std::path::PathBuf {
+        if account == DEFAULT_ACCOUNT {
+            self.cache_path.clone()
+        } else {
+            self.cache_path.join("accounts").join(account)
+        }
+    }
+    fn account_path(&self) -> std::path::PathBuf {
+        self.account_dir(&self.current_account)
+    }
+    /// Another profile that's already signed in as the Twitch user `user_id`, if there is one.
+    fn account_signed_in_as(&self, user_id: &str) -> Option<String> {
+        self.accounts
+            .iter()
+            .filter(|account| **account != self.current_account)
+            .find(|account| settings::read(&self.account_dir(account), "user_id.txt") == user_id)
+            .cloned()
+    }
+    /// Loads the credentials of `current_account` and signs in with them.
+    fn load_account(&mut self) -> Task<Message> {
+        let account_path = self.account_path();
+        let credentials = self.credentials();
+        for name in ["access_token.txt", "refresh_token.txt", "oauth_token.txt"] {
+            credentials.migrate(name);
+        }
+        self.twitch_oauth_token = credentials.read("oauth_token.txt").unwrap_or_default();
+        self.twitch_oauth_token_input = self.twitch_oauth_token.clone();
+        self.refresh_token = credentials.read("refresh_token.txt");
+        self.token = None;
+        self.auth_status = AuthStatus::Unknown;
+        self.followed_streams = HashMap::new();
+        self.stream_order = Vec::new();
+        self.currently_streaming_broadcasters = Vec::new();
+        self.login_error = None;
+        self.offline = false;
+        self.followed_channels = Vec::new();
+        self.missing_features = Vec::new();
+        // Whatever was still being fetched belongs to the previous account.
+        self.account_generation += 1;
+        self.refresh_in_flight = false;
+        self.followed_channels_loading = false;
+        self.favorites = std::fs::read_to_string(account_path.join("favorites.txt"))
+            .unwrap_or_default()
+            .lines()
+            .map(String::from)
+            .collect();
+        self.hidden = BroadcasterList::load(&account_path, "hidden.txt");
+        self.muted = BroadcasterList::load(&account_path, "muted.txt");
+        // Show the streams from last time until fresh ones come in, or for good if Twitch can't be reached.
+        let snapshot = Snapshot::load(&account_path);
+        self.last_updated = snapshot.as_ref().map(|snapshot| snapshot.updated_at);
+        let snapshot_streams = snapshot.map(|snapshot| snapshot.streams).unwrap_or_default();
+        self.stream_order = snapshot_streams.iter().map(|stream| stream.user_id.to_string()).collect();
+        for stream in snapshot_streams.iter() {
+            self.followed_streams.insert(
+                stream.user_id.to_string(),
+                FollowedStream { stream: stream.clone(), thumbnail: ImageState::Loading },
+            );
+        }
+        self.sort_streams();
+        let thumbnails = get_thumnails(self.image_cache.clone(), snapshot_streams, self.thumbnail_concurrency);
+        let login_mode = std::fs::read_to_string(account_path.join("login_mode.txt")).unwrap_or_default();
+        let login = match credentials.read("access_token.txt") {
+            Some(access_token_string) => {
+                self.login_required = false;
+                self.update(Message::Startup(access_token_string))
+            }
+            None if login_mode.trim() == "device" => self.update(Message::StartDeviceLogin),
+            None => self.update(Message::StartLogin),
+        };
+        Task::batch([thumbnails, login])
+    }
+    fn credentials(&self) -> credential_store::ChainStore {
+        credential_store::for_directory(self.account_path())
+    }
+    /// The scopes to ask for when signing in: the base features' and those of features waiting for permission,
+    /// plus whatever was granted before.
+    fn login_scopes(&self) -> Vec<Scope> {
+        let features: Vec<Feature> = Feature::BASE.iter().chain(self.missing_features.iter()).copied().collect();
+        let mut login_scopes = scopes::required_scopes(&features);
+        if let Some(token) = &self.token {
+            for scope in token.scopes() {
+                if !login_scopes.contains(scope) {
+                    login_scopes.push(scope.clone());
+                }
+            }
+        }
+        login_scopes
+    }
+    fn save_tokens(&mut self, access_token: &str, refresh_token: Option<String>, login_mode: &str) -> Result<(), String> {
+        let credentials = self.credentials();
+        self.refresh_token = refresh_token.clone();
+        credentials
+            .write("access_token.txt", access_token)
+            .map_err(|e| format!("Could not save the access token: {e}"))?;
+        match &refresh_token {
+            Some(refresh_token) => credentials
+                .write("refresh_token.txt", refresh_token)
+                .map_err(|e| format!("Could not save the refresh token: {e}"))?,
+            None => credentials.remove("refresh_token.txt"),
+        }
+        std::fs::write(self.account_path().join("login_mode.txt"), login_mode.as_bytes())
+            .map_err(|e| format!("Could not save the login mode: {e}"))
+    }
+    /// Shows a failed write in the error banner; the change still applies until the app is closed.
+    fn report_save(&mut self, what: &str, result: std::io::Result<()>) {
+        if let Err(e) = result {
+            self.fetch_error = Some(format!("Could not save {what}: {e}"));
+        }
+    }
+    /// Shows why signing in failed: on the login screen, or in the error banner when only granting more permissions.
+    fn login_failed(&mut self, error: String) {
+        if self.granting_permissions {
+            self.fetch_error = Some(error);
+        } else {
+            self.login_error = Some(error);
+        }
+        self.granting_permissions = false;
+    }
+    fn try_refresh(&mut self) -> Option<Task<Message>> {
+        let refresh_token = self.refresh_token.take()?;
+        let generation = self.account_generation;
+        Some(Task::perform(
+            device_flow::refresh_access_token(self.http_client.clone(), self.twitch_config.oauth2_url(), self.twitch_config.client_id(), refresh_token),
+            move |result| Message::GotRefreshedToken(generation, result),
+        ))
+    }
+    /// The streams to show, leaving out hidden broadcasters: all of them in the chosen order, or the ones matching
+    /// the search, best match first.
+    fn visible_streams(&self) -> Vec<String> {
+        let shown = self.stream_order.iter().filter(|user_id| !self.hidden.contains(user_id));
+        if self.search_input.trim().is_empty() {
+            return shown.cloned().collect();
         }
+        let mut matches: Vec<(u32, &String)> = shown
+            .filter_map(|user_id| {
+                let followed_stream = self.followed_streams.get(user_id)?;
+                Some((search::score(&self.search_input, &followed_stream.stream)?, user_id))
+            })
+            .collect();
+        matches.sort_by(|(a, _), (b, _)| b.cmp(a));
+        matches.into_iter().map(|(_, user_id)| user_id.clone()).collect()
+    }
+    /// Fetches the box art of the games being played that we don't have yet, when grouping by game.
+    fn fetch_box_art(&self) -> Task<Message> {
+        let Some(token) = self.token.clone().filter(|_| self.group_by_game) else {
+            return Task::none();
+        };
+        let mut game_ids: Vec<CategoryId> = Vec::new();
+        for followed_stream in self.followed_streams.values() {
+            let game_id = &followed_stream.stream.game_id;
+            if !game_id.as_str().is_empty() && !self.box_art.contains_key(game_id.as_str()) && !game_ids.contains(game_id) {
+                game_ids.push(game_id.clone());
+            }
+        }
+        if game_ids.is_empty() {
+            return Task::none();
+        }
+        Task::perform(
+            get_box_art(self.client.clone(), token, self.image_cache.clone(), game_ids),
+            Message::GotBoxArt,
+        )
+    }
+    /// Fetches everyone the user follows for the All follows tab, unless that's already happening.
+    fn load_followed_channels(&mut self) -> Task<Message> {
+        let Some(token) = self.token.clone() else {
+            return Task::none();
+        };
+        if !Feature::AllFollows.is_granted(token.scopes()) {
+            // Ask for the permission now that it's needed.
+            if !self.missing_features.contains(&Feature::AllFollows) {
+                self.missing_features.push(Feature::AllFollows);
+            }
+            return Task::none();
+        }
+        if self.followed_channels_loading {
+            return Task::none();
+        }
+        self.followed_channels_loading = true;
+        let generation = self.account_generation;
+        Task::perform(get_followed_channels(self.client.clone(), token), move |result| {
+            Message::GotFollowedChannels(generation, result)
+        })
+    }
+    fn sort_streams(&mut self) {
+        let followed_streams = &self.followed_streams;
+        let sort_order = self.sort_order;
+        self.stream_order.sort_by(|a, b| match (followed_streams.get(a), followed_streams.get(b)) {
+            (Some(a), Some(b)) => sort_order.compare(&a.stream, &b.stream),
+            _ => std::cmp::Ordering::Equal,
+        });
+    }
+    /// Fetches the followed streams and their thumbnails, unless that's already happening.
+    fn start_refresh(&mut self, token: UserToken) -> Task<Message> {
+        if self.refresh_in_flight {
+            return Task::none();
+        }
+        self.refresh_in_flight = true;
+        Task::perform(
+            get_followed_streams(self.client.clone(), token),
+            fetch_result(self.account_generation, Message::SaveRefresh, Message::RefreshFailed),
+        )
     }
     fn subscription(&self) -> Subscription<Message> {
         Subscription::batch(vec![
         event::listen().map(Message::EventOccurred),
-            time::every(Duration::from_secs(60)).map(Message::OneMinute)
+            time::every(Duration::from_secs(60)).map(Message::OneMinute),
+            time::every(Duration::from_secs(60 * 60)).map(Message::OneHour),
         ])
     }
     fn update(&mut self, message: Message) -> Task<Message> {
         match message {
-            Message::CheckAndNotifyNewStreams(streams) => {
+            Message::SaveRefresh(generation, _)
+            | Message::CheckAndNotifyNewStreams(generation, _)
+            | Message::RefreshFailed(generation, _)
+            | Message::FetchFailed(generation, _)
+            | Message::GotFollowedChannels(generation, _)
+            | Message::GotUserToken(generation, _)
+            | Message::GotRefreshedToken(generation, _)
+            | Message::TokenValidated(generation, _)
+            | Message::GotAccessToken(generation, _)
+            | Message::GotDeviceCode(generation, _)
+            | Message::GotDeviceToken(generation, _)
+                if generation != self.account_generation =>
+            {
+                // Fetched for an account that's been switched away from or signed out of.
+                Task::none()
+            }
+            Message::CheckAndNotifyNewStreams(generation, streams) => {
                 let mut new_broadcasters: Vec<String> = Vec::new();
+                let mut new_favorite_broadcasters: Vec<String> = Vec::new();
                 let mut new_current_broadcasters: Vec<String> = Vec::new();
-                for stream in streams {
+                for stream in streams.iter() {
                     let this_broadcaster = stream.user_login.to_string();
-                    if !self.currently_streaming_broadcasters.contains(&this_broadcaster) {
-                        new_broadcasters.push(this_broadcaster.clone());
+                    // Muted broadcasters still count as streaming, so unmuting doesn't set off a notification.
+                    if !self.currently_streaming_broadcasters.contains(&this_broadcaster) && !self.muted.contains(stream.user_id.as_str()) {
+                        if self.favorites.iter().any(|favorite| favorite == stream.user_id.as_str()) {
+                            new_favorite_broadcasters.push(this_broadcaster.clone());
+                        } else {
+                            new_broadcasters.push(this_broadcaster.clone());
+                        }
                     }
                     new_current_broadcasters.push(this_broadcaster.clone());
                 }
                 self.currently_streaming_broadcasters = new_current_broadcasters.clone();
-                if new_broadcasters.len() > 0 {
+                if !new_favorite_broadcasters.is_empty() {
+                    let notif_message = format!("Your favorites have started streaming: {}", new_favorite_broadcasters.join(", "));
+                    notifications::notify(&notif_message, self.favorite_urgency);
+                }
+                if !new_broadcasters.is_empty() {
                     let notif_message = format!("The following streamers have started streaming: {}", new_broadcasters.join(", "));
-                    notify_rust::Notification::new().summary("Iced Twitch GUI").body(notif_message.as_str()).show().unwrap();
+                    notifications::notify(&notif_message, Urgency::Normal);
+                }
+                if self.offline {
+                    // Twitch is back, so swap the snapshot for the live streams.
+                    return self.update(Message::SaveRefresh(generation, streams));
+                }
+                if streams.iter().any(|stream| !self.followed_streams.contains_key(stream.user_id.as_str())) {
+                    // Someone went live, so move them from All follows to the grid.
+                    return self.update(Message::SaveRefresh(generation, streams));
                 }
                 Task::none()
 
             }
             Message::OneMinute(_instant) => {
-                Task::perform(get_followed_streams(self.client.clone(), self.token.clone().unwrap()), Message::CheckAndNotifyNewStreams)
+                self.now = SystemTime::now();
+                match self.token.clone() {
+                    Some(_) if self.refresh_in_flight => Task::none(),
+                    Some(token) => Task::perform(get_followed_streams(self.client.clone(), token), fetch_result(self.account_generation, Message::CheckAndNotifyNewStreams, Message::FetchFailed)),
+                    // Twitch was unreachable at startup, so try the saved access token again.
+                    None if self.offline && !self.login_required => match self.credentials().read("access_token.txt") {
+                        Some(access_token_string) => self.update(Message::Startup(access_token_string)),
+                        None => Task::none(),
+                    },
+                    None => Task::none(),
+                }
+            }
+            Message::OneHour(_instant) => match self.token.clone() {
+                Some(token) => {
+                    let generation = self.account_generation;
+                    Task::perform(
+                        validate_user_token(self.client.clone(), token, self.refresh_token.is_some()),
+                        move |result| Message::TokenValidated(generation, result),
+                    )
+                }
+                None => Task::none(),
+            },
+            Message::TokenValidated(_, Ok(auth_status)) => {
+                self.auth_status = auth_status;
+                if auth_status == AuthStatus::Invalid {
+                    self.token = None;
+                }
+                if auth_status != AuthStatus::Valid {
+                    if let Some(task) = self.try_refresh() {
+                        return task;
+                    }
+                }
+                Task::none()
+            }
+            Message::TokenValidated(_, Err(error)) => {
+                self.fetch_error = Some(error);
+                Task::none()
+            }
+            Message::RefreshFailed(generation, error) => {
+                self.refresh_in_flight = false;
+                self.update(Message::FetchFailed(generation, error))
+            }
+            Message::FetchFailed(_, FetchError::Unreachable(error)) => {
+                self.offline = true;
+                self.fetch_error = Some(error);
+                Task::none()
+            }
+            Message::FetchFailed(_, FetchError::Unauthorized(error)) => {
+                // Stop polling with the dead token.
+                self.token = None;
+                self.auth_status = AuthStatus::Invalid;
+                self.fetch_error = Some(error);
+                self.try_refresh().unwrap_or(Task::none())
+            }
+            Message::FetchFailed(_, FetchError::Failed(error)) => {
+                self.fetch_error = Some(error);
+                Task::none()
+            }
+            Message::DismissError => {
+                self.fetch_error = None;
+                Task::none()
             }
             Message::StreamCommandTextInputChanged(new_si) => {
                 self.stream_command_input = new_si.clone();
@@ -182,6 +1033,197 @@ impl IcedTwitchGui {
                 self.twitch_oauth_token_input = new_oi.clone();
                 Task::none()
             }
+            Message::RedirectPortTextInputChanged(new_pi) => {
+                self.redirect_port_input = new_pi.chars().filter(|c| c.is_ascii_digit()).collect();
+                Task::none()
+            }
+            Message::ThumbnailConcurrencyTextInputChanged(new_ci) => {
+                self.thumbnail_concurrency_input = new_ci.chars().filter(|c| c.is_ascii_digit()).collect();
+                Task::none()
+            }
+            Message::ImageCacheTtlTextInputChanged(new_ti) => {
+                self.image_cache_ttl_input = new_ti.chars().filter(|c| c.is_ascii_digit()).collect();
+                Task::none()
+            }
+            Message::ImageCacheSizeTextInputChanged(new_si) => {
+                self.image_cache_size_input = new_si.chars().filter(|c| c.is_ascii_digit()).collect();
+                Task::none()
+            }
+            Message::HelixUrlTextInputChanged(new_hi) => {
+                self.helix_url_input = new_hi;
+                Task::none()
+            }
+            Message::OAuth2UrlTextInputChanged(new_oi) => {
+                self.oauth2_url_input = new_oi;
+                Task::none()
+            }
+            Message::ClientIdTextInputChanged(new_ci) => {
+                self.client_id_input = new_ci;
+                Task::none()
+            }
+            Message::ProxyUrlTextInputChanged(new_pi) => {
+                self.proxy_url_input = new_pi;
+                Task::none()
+            }
+            Message::CaBundlePathTextInputChanged(new_ci) => {
+                self.ca_bundle_path_input = new_ci;
+                Task::none()
+            }
+            Message::SortOrderSelected(sort_order) => {
+                self.sort_order = sort_order;
+                let result = self.sort_order.save(&self.cache_path);
+                self.report_save("the sort order", result);
+                self.sort_streams();
+                Task::none()
+            }
+            Message::GroupByGameToggled(group_by_game) => {
+                self.group_by_game = group_by_game;
+                let result = std::fs::write(self.cache_path.join("group_by_game.txt"), self.group_by_game.to_string().as_bytes());
+                self.report_save("the grouping", result);
+                self.fetch_box_art()
+            }
+            Message::ToggleGameSection(game_id) => {
+                if let Some(index) = self.collapsed_games.iter().position(|collapsed| *collapsed == game_id) {
+                    self.collapsed_games.remove(index);
+                } else {
+                    self.collapsed_games.push(game_id);
+                }
+                let result = std::fs::write(self.cache_path.join("collapsed_games.txt"), self.collapsed_games.join("\n").as_bytes());
+                self.report_save("the collapsed games", result);
+                Task::none()
+            }
+            Message::ToggleFavorite(user_id) => {
+                if let Some(index) = self.favorites.iter().position(|favorite| *favorite == user_id) {
+                    self.favorites.remove(index);
+                } else {
+                    self.favorites.push(user_id);
+                }
+                let result = std::fs::write(self.account_path().join("favorites.txt"), self.favorites.join("\n").as_bytes());
+                self.report_save("your favorites", result);
+                Task::none()
+            }
+            Message::ToggleHidden(user_id) => {
+                let login = self.followed_streams.get(&user_id).map(|followed_stream| followed_stream.stream.user_login.to_string()).unwrap_or_default();
+                self.hidden.toggle(&user_id, &login);
+                let result = self.hidden.save(&self.account_path(), "hidden.txt");
+                self.report_save("the hidden broadcasters", result);
+                Task::none()
+            }
+            Message::ToggleMuted(user_id) => {
+                let login = self.followed_streams.get(&user_id).map(|followed_stream| followed_stream.stream.user_login.to_string()).unwrap_or_default();
+                self.muted.toggle(&user_id, &login);
+                let result = self.muted.save(&self.account_path(), "muted.txt");
+                self.report_save("the muted broadcasters", result);
+                Task::none()
+            }
+            Message::SelectTab(tab) => {
+                self.tab = tab;
+                if tab == Tab::AllFollows && self.followed_channels.is_empty() {
+                    return self.load_followed_channels();
+                }
+                Task::none()
+            }
+            Message::GotFollowedChannels(_, Ok(followed_channels)) => {
+                self.followed_channels_loading = false;
+                // Keep what we already know until the details come in again.
+                let old_channels = std::mem::replace(&mut self.followed_channels, followed_channels);
+                for channel in self.followed_channels.iter_mut() {
+                    if let Some(old_channel) = old_channels.iter().find(|old_channel| old_channel.user_id == channel.user_id) {
+                        channel.avatar = old_channel.avatar.clone();
+                        channel.last_broadcast = old_channel.last_broadcast;
+                    }
+                }
+                let Some(token) = self.token.clone() else {
+                    return Task::none();
+                };
+                // Live channels aren't listed, and what's already known doesn't need fetching again.
+                let missing_details: Vec<FollowedChannel> = self
+                    .followed_channels
+                    .iter()
+                    .filter(|channel| !self.followed_streams.contains_key(&channel.user_id))
+                    .filter(|channel| channel.avatar.is_none() || channel.last_broadcast.is_none())
+                    .cloned()
+                    .collect();
+                get_channel_details(
+                    self.client.clone(),
+                    token,
+                    self.image_cache.clone(),
+                    missing_details,
+                    self.thumbnail_concurrency,
+                )
+            }
+            Message::GotFollowedChannels(_, Err(error)) => {
+                self.followed_channels_loading = false;
+                self.fetch_error = Some(error);
+                Task::none()
+            }
+            Message::GotChannelDetails(user_id, avatar, last_broadcast) => {
+                if let Some(channel) = self.followed_channels.iter_mut().find(|channel| channel.user_id == user_id) {
+                    channel.avatar = avatar.or(channel.avatar.take());
+                    channel.last_broadcast = last_broadcast.or(channel.last_broadcast);
+                }
+                Task::none()
+            }
+            Message::OpenDetails(user_id) => {
+                let Some(stream) = self.followed_streams.get(&user_id).map(|followed_stream| followed_stream.stream.clone()) else {
+                    return Task::none();
+                };
+                self.details = Some(user_id.clone());
+                self.details_thumbnail = ImageState::Loading;
+                let thumbnail = Task::perform(
+                    get_thumnail(self.image_cache.clone(), stream, 640, 360),
+                    |(user_id, result)| Message::GotDetailsThumbnail(user_id, result),
+                );
+                match self.token.clone() {
+                    Some(token) if !matches!(self.channel_descriptions.get(&user_id), Some(Ok(_))) => Task::batch([
+                        thumbnail,
+                        Task::perform(
+                            get_channel_description(self.client.clone(), token, user_id),
+                            |(user_id, result)| Message::GotChannelDescription(user_id, result),
+                        ),
+                    ]),
+                    _ => thumbnail,
+                }
+            }
+            Message::CloseDetails => {
+                self.details = None;
+                Task::none()
+            }
+            Message::GotDetailsThumbnail(user_id, result) => {
+                if self.details.as_ref() == Some(&user_id) {
+                    self.details_thumbnail = match result {
+                        Ok(handle) => ImageState::Loaded(handle),
+                        Err(error) => ImageState::Failed { error, attempts: 1 },
+                    };
+                }
+                Task::none()
+            }
+            Message::GotChannelDescription(user_id, result) => {
+                self.channel_descriptions.insert(user_id, result);
+                Task::none()
+            }
+            Message::BroadcasterListsToggle => {
+                self.broadcaster_lists_open = !self.broadcaster_lists_open;
+                Task::none()
+            }
+            Message::FavoriteUrgencySelected(urgency) => {
+                self.favorite_urgency_input = urgency;
+                Task::none()
+            }
+            Message::GotBoxArt(box_art) => {
+                self.box_art.extend(box_art);
+                Task::none()
+            }
+            Message::SearchTextInputChanged(new_si) => {
+                self.search_input = new_si;
+                Task::none()
+            }
+            // Without a search there's no match to play.
+            Message::PlayTopMatch if self.search_input.trim().is_empty() => Task::none(),
+            Message::PlayTopMatch => match self.visible_streams().first() {
+                Some(user_id) => self.update(Message::ClickedStream(user_id.clone())),
+                None => Task::none(),
+            },
             Message::SettingsToggle => {
                 self.settings_open = !self.settings_open;
                 Task::none()
@@ -190,6 +1232,55 @@ impl IcedTwitchGui {
                 self.stream_command = self.stream_command_input.clone();
                 self.player_command = self.player_command_input.clone();
                 self.twitch_oauth_token = self.twitch_oauth_token_input.clone();
+                if let Ok(port) = self.redirect_port_input.parse() {
+                    self.redirect_port = port;
+                    let result = std::fs::write(self.cache_path.join("redirect_port.txt"), self.redirect_port.to_string().as_bytes());
+                    self.report_save("the redirect port", result);
+                }
+                self.redirect_port_input = self.redirect_port.to_string();
+                // The base URLs and client ID are only read at startup.
+                let result = TwitchConfig {
+                    helix_url: self.helix_url_input.clone(),
+                    oauth2_url: self.oauth2_url_input.clone(),
+                    client_id: self.client_id_input.clone(),
+                }
+                .save(&self.cache_path);
+                self.report_save("the Twitch settings", result);
+                let network_config = NetworkConfig {
+                    proxy_url: self.proxy_url_input.clone(),
+                    ca_bundle_path: self.ca_bundle_path_input.clone(),
+                };
+                match network_config.client() {
+                    Ok(http_client) => {
+                        let result = network_config.save(&self.cache_path);
+                        self.report_save("the network settings", result);
+                        self.client = HelixClient::with_client(ScheduledClient::new(http_client.clone()));
+                        self.image_cache.client = http_client.clone();
+                        self.http_client = http_client;
+                    }
+                    Err(error) => self.fetch_error = Some(error),
+                }
+                if let Some(concurrency) = self.thumbnail_concurrency_input.parse().ok().filter(|concurrency| *concurrency > 0) {
+                    self.thumbnail_concurrency = concurrency;
+                    let result = std::fs::write(self.cache_path.join("thumbnail_concurrency.txt"), self.thumbnail_concurrency.to_string().as_bytes());
+                    self.report_save("the thumbnail concurrency", result);
+                }
+                self.thumbnail_concurrency_input = self.thumbnail_concurrency.to_string();
+                if let Ok(minutes) = self.image_cache_ttl_input.parse::<u64>() {
+                    self.image_cache.preview_ttl = Duration::from_secs(minutes * 60);
+                    let result = std::fs::write(self.cache_path.join("image_cache_ttl_minutes.txt"), minutes.to_string().as_bytes());
+                    self.report_save("the image cache lifetime", result);
+                }
+                self.image_cache_ttl_input = (self.image_cache.preview_ttl.as_secs() / 60).to_string();
+                if let Ok(mb) = self.image_cache_size_input.parse::<u64>() {
+                    self.image_cache.max_bytes = mb * 1024 * 1024;
+                    let result = std::fs::write(self.cache_path.join("image_cache_size_mb.txt"), mb.to_string().as_bytes());
+                    self.report_save("the image cache size", result);
+                }
+                self.image_cache_size_input = (self.image_cache.max_bytes / 1024 / 1024).to_string();
+                self.favorite_urgency = self.favorite_urgency_input;
+                let result = self.favorite_urgency.save(&self.cache_path, "favorite_urgency.txt");
+                self.report_save("the notification urgency", result);
                 {
                     let mut f =
                         std::fs::File::create(self.cache_path.join("stream_command.txt")).unwrap();
@@ -200,10 +1291,12 @@ impl IcedTwitchGui {
                         std::fs::File::create(self.cache_path.join("player_command.txt")).unwrap();
                     f.write_all(self.player_command.as_bytes()).unwrap();
                 }
-                {
-                    let mut f =
-                        std::fs::File::create(self.cache_path.join("oauth_token.txt")).unwrap();
-                    f.write_all(self.twitch_oauth_token.as_bytes()).unwrap();
+                if self.twitch_oauth_token.is_empty() {
+                    self.credentials().remove("oauth_token.txt");
+                } else {
+                    if let Err(e) = self.credentials().write("oauth_token.txt", &self.twitch_oauth_token) {
+                        self.fetch_error = Some(format!("Could not save the OAuth token: {e}"));
+                    }
                 }
                 self.settings_open = false;
                 Task::none()
@@ -219,55 +1312,314 @@ impl IcedTwitchGui {
                 {
                     self.num_columns = (size.width / 320.0).floor() as usize;
                 }
-                Task::none()
+                match evnt {
+                    Event::Keyboard(keyboard::Event::KeyPressed {
+                        key: keyboard::Key::Character(c),
+                        modifiers,
+                        ..
+                    }) if c.as_str() == "f" && modifiers.command() => text_input::focus(SEARCH_INPUT),
+                    Event::Keyboard(keyboard::Event::KeyPressed {
+                        key: keyboard::Key::Named(keyboard::key::Named::Escape),
+                        ..
+                    }) => self.update(Message::CloseDetails),
+                    _ => Task::none(),
+                }
             }
-            Message::OpenChannel(idx) => {
-                webbrowser::open(
-                    format!(
-                        "https://www.twitch.tv/{}",
-                        self.followed_streams[idx].user_login
-                    )
-                    .as_str(),
-                )
-                .unwrap();
+            Message::OpenChannel(user_id) => {
+                let login = match self.followed_streams.get(&user_id) {
+                    Some(followed_stream) => Some(followed_stream.stream.user_login.to_string()),
+                    None => self
+                        .followed_channels
+                        .iter()
+                        .find(|channel| channel.user_id == user_id)
+                        .map(|channel| channel.login.clone()),
+                };
+                if let Some(login) = login {
+                    let _ = webbrowser::open(format!("https://www.twitch.tv/{login}").as_str());
+                }
                 Task::none()
             }
-            Message::OpenChat(idx) => {
-                webbrowser::open(
-                    format!(
-                        "https://www.twitch.tv/popout/{}/chat",
-                        self.followed_streams[idx].user_login
-                    )
-                    .as_str(),
-                )
-                .unwrap();
+            Message::OpenChat(user_id) => {
+                if let Some(followed_stream) = self.followed_streams.get(&user_id) {
+                    let _ = webbrowser::open(
+                        format!(
+                            "https://www.twitch.tv/popout/{}/chat",
+                            followed_stream.stream.user_login
+                        )
+                        .as_str(),
+                    );
+                }
                 Task::none()
             }
             Message::GotChildProcessId(_pid_option) => Task::none(),
-            Message::GotUserToken(user_token) => {
-                self.token = Some(user_token);
+            Message::GotUserToken(_, Ok(user_token)) => {
+                if let Some(account) = self.account_signed_in_as(user_token.user_id.as_str()) {
+                    let credentials = self.credentials();
+                    for name in ["access_token.txt", "refresh_token.txt"] {
+                        credentials.remove(name);
+                    }
+                    self.refresh_token = None;
+                    self.token = None;
+                    self.auth_status = AuthStatus::Invalid;
+                    self.granting_permissions = false;
+                    self.login_required = true;
+                    self.login_error = Some(format!("{} is already signed in on the {account} profile.", user_token.login));
+                    return Task::none();
+                }
+                let result = std::fs::write(self.account_path().join("user_id.txt"), user_token.user_id.as_str().as_bytes());
+                self.report_save("the signed in user", result);
+                self.auth_status = if user_token.never_expires() {
+                    AuthStatus::Valid
+                } else {
+                    AuthStatus::from_expires_in(Some(user_token.expires_in()), self.refresh_token.is_some())
+                };
+                // Features asked for before this token was granted stay on the prompt until they're covered.
+                let wanted: Vec<Feature> = Feature::BASE.iter().chain(self.missing_features.iter()).copied().collect();
+                self.missing_features = scopes::missing_features(user_token.scopes(), &wanted);
+                self.token = Some(user_token.clone());
+                self.login_required = false;
+                self.login_error = None;
+                let mut tasks = vec![self.start_refresh(user_token)];
+                // Renew it now rather than letting it run out before the next hourly check.
+                if self.auth_status == AuthStatus::ExpiringSoon {
+                    tasks.extend(self.try_refresh());
+                }
+                if self.tab == Tab::AllFollows && self.followed_channels.is_empty() {
+                    tasks.push(self.load_followed_channels());
+                }
+                Task::batch(tasks)
+            }
+            Message::GotUserToken(_, Err(TokenError::Unreachable(error))) => {
+                self.offline = true;
+                self.fetch_error = Some(error);
+                Task::none()
+            }
+            Message::GotUserToken(_, Err(TokenError::Rejected(error))) => {
+                if let Some(task) = self.try_refresh() {
+                    return task;
+                }
+                self.token = None;
+                self.auth_status = AuthStatus::Invalid;
+                self.login_required = true;
+                self.login_error = Some(error);
+                Task::none()
+            }
+            Message::StartLogin => {
+                self.login_required = !self.granting_permissions;
+                if self.login_in_progress {
+                    return Task::none();
+                }
+                self.login_in_progress = true;
+                self.login_error = None;
+                let generation = self.account_generation;
                 Task::perform(
-                    fetch_followed_streams_get_thumnails(
-                        self.client.clone(),
-                        self.token.clone().unwrap(),
-                    ),
-                    Message::SaveRefresh,
+                    redirect_login(self.twitch_config.client_id(), self.redirect_port, self.login_scopes(), self.accounts.len() > 1),
+                    move |result| Message::GotAccessToken(generation, result),
+                )
+            }
+            Message::GotAccessToken(_, result) => {
+                self.login_in_progress = false;
+                match result {
+                    Ok(access_token_string) => match self.save_tokens(&access_token_string, None, "browser") {
+                        Ok(()) => {
+                            self.granting_permissions = false;
+                            self.update(Message::Startup(access_token_string))
+                        }
+                        Err(error) => {
+                            self.login_failed(error);
+                            Task::none()
+                        }
+                    },
+                    Err(error) => {
+                        self.login_failed(error.to_string());
+                        Task::none()
+                    }
+                }
+            }
+            Message::Startup(access_token_string) => {
+                let generation = self.account_generation;
+                Task::perform(
+                    get_user_token(self.client.clone(), access_token_string),
+                    move |result| Message::GotUserToken(generation, result),
                 )
             }
-            Message::Startup(access_token_string) => Task::perform(
-                get_user_token(self.client.clone(), access_token_string),
-                Message::GotUserToken,
-            ),
 
-            Message::Refresh => Task::perform(
-                fetch_followed_streams_get_thumnails(
-                    self.client.clone(),
-                    self.token.clone().unwrap(),
-                ),
-                Message::SaveRefresh,
-            ),
-            Message::ClickedStream(idx) => {
-                let this_stream = self.followed_streams[idx].clone();
+            Message::StartDeviceLogin => {
+                self.login_required = !self.granting_permissions;
+                if self.login_in_progress {
+                    return Task::none();
+                }
+                self.login_in_progress = true;
+                self.login_error = None;
+                let generation = self.account_generation;
+                Task::perform(
+                    device_flow::request_device_code(self.http_client.clone(), self.twitch_config.oauth2_url(), self.twitch_config.client_id(), self.login_scopes()),
+                    move |result| Message::GotDeviceCode(generation, result),
+                )
+            }
+            Message::GotDeviceCode(generation, Ok(device_code)) => {
+                self.device_code = Some(device_code.clone());
+                Task::perform(
+                    device_flow::poll_device_token(self.http_client.clone(), self.twitch_config.oauth2_url(), self.twitch_config.client_id(), self.login_scopes(), device_code),
+                    move |result| Message::GotDeviceToken(generation, result),
+                )
+            }
+            Message::GotDeviceCode(_, Err(error)) => {
+                self.login_in_progress = false;
+                self.login_failed(error);
+                Task::none()
+            }
+            Message::OpenVerificationUri => {
+                if let Some(device_code) = &self.device_code {
+                    let _ = webbrowser::open(device_code.verification_uri.as_str());
+                }
+                Task::none()
+            }
+            Message::GotDeviceToken(_, result) => {
+                self.login_in_progress = false;
+                self.device_code = None;
+                match result {
+                    Ok(device_token) => match self.save_tokens(&device_token.access_token, device_token.refresh_token, "device") {
+                        Ok(()) => {
+                            self.granting_permissions = false;
+                            self.update(Message::Startup(device_token.access_token))
+                        }
+                        Err(error) => {
+                            self.login_failed(error);
+                            Task::none()
+                        }
+                    },
+                    Err(error) => {
+                        self.login_failed(error);
+                        Task::none()
+                    }
+                }
+            }
+            Message::GotRefreshedToken(_, Ok(device_token)) => {
+                // The new tokens still work for this session even if they couldn't be saved.
+                if let Err(error) = self.save_tokens(&device_token.access_token, device_token.refresh_token, "device") {
+                    self.fetch_error = Some(error);
+                }
+                self.update(Message::Startup(device_token.access_token))
+            }
+            Message::GotRefreshedToken(_, Err(RefreshError::Unreachable(error))) => {
+                // The refresh token is still good, so keep both tokens and try again on the next check.
+                self.refresh_token = self.credentials().read("refresh_token.txt");
+                if self.token.is_none() {
+                    self.offline = true;
+                }
+                self.fetch_error = Some(error);
+                Task::none()
+            }
+            Message::GotRefreshedToken(_, Err(RefreshError::Rejected(error))) => {
+                self.credentials().remove("refresh_token.txt");
+                self.token = None;
+                self.auth_status = AuthStatus::Invalid;
+                self.login_required = true;
+                self.login_error = Some(error);
+                Task::none()
+            }
+            Message::SwitchAccount(account) => {
+                if self.login_in_progress || account == self.current_account {
+                    return Task::none();
+                }
+                self.current_account = account;
+                let result = std::fs::write(self.cache_path.join("current_account.txt"), self.current_account.as_bytes());
+                self.report_save("the current account", result);
+                self.load_account()
+            }
+            Message::NewAccountTextInputChanged(new_ai) => {
+                self.new_account_input = new_ai
+                    .chars()
+                    .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+                    .collect();
+                Task::none()
+            }
+            Message::AddAccount => {
+                let account = self.new_account_input.clone();
+                if account.is_empty() || self.login_in_progress || self.accounts.contains(&account) {
+                    return Task::none();
+                }
+                if let Err(e) = std::fs::create_dir_all(self.account_dir(&account)) {
+                    self.fetch_error = Some(format!("Could not create the {account} account: {e}"));
+                    return Task::none();
+                }
+                self.accounts.push(account.clone());
+                self.new_account_input = String::new();
+                self.settings_open = false;
+                self.update(Message::SwitchAccount(account))
+            }
+            Message::GrantPermissions => {
+                if self.login_in_progress {
+                    return Task::none();
+                }
+                self.granting_permissions = true;
+                let login_mode = std::fs::read_to_string(self.account_path().join("login_mode.txt")).unwrap_or_default();
+                if login_mode.trim() == "device" {
+                    self.update(Message::StartDeviceLogin)
+                } else {
+                    self.update(Message::StartLogin)
+                }
+            }
+            Message::DismissPermissionPrompt => {
+                self.missing_features = Vec::new();
+                Task::none()
+            }
+            Message::SignOut => {
+                let credentials = self.credentials();
+                for name in ["access_token.txt", "refresh_token.txt", "oauth_token.txt"] {
+                    credentials.remove(name);
+                }
+                let _ = std::fs::remove_file(self.account_path().join("login_mode.txt"));
+                let _ = std::fs::remove_file(self.account_path().join("user_id.txt"));
+                Snapshot::remove(&self.account_path());
+                let token = self.token.take();
+                self.refresh_token = None;
+                self.twitch_oauth_token = String::new();
+                self.twitch_oauth_token_input = String::new();
+                self.followed_streams = HashMap::new();
+                self.stream_order = Vec::new();
+                self.currently_streaming_broadcasters = Vec::new();
+                self.missing_features = Vec::new();
+                self.offline = false;
+                self.last_updated = None;
+                self.followed_channels = Vec::new();
+                self.tab = Tab::Live;
+                // Anything still being fetched was for the signed out session.
+                self.account_generation += 1;
+                self.refresh_in_flight = false;
+                self.followed_channels_loading = false;
+                self.login_in_progress = false;
+                self.granting_permissions = false;
+                self.device_code = None;
+                self.auth_status = AuthStatus::Unknown;
+                self.settings_open = false;
+                self.login_required = true;
+                self.login_error = None;
+                match token {
+                    Some(token) => Task::perform(
+                        revoke_user_token(self.client.clone(), token),
+                        Message::SignedOut,
+                    ),
+                    None => Task::none(),
+                }
+            }
+            Message::SignedOut(result) => {
+                self.login_error = result.err();
+                Task::none()
+            }
+            Message::Refresh => match self.token.clone() {
+                Some(token) if self.tab == Tab::AllFollows => {
+                    let followed_channels = self.load_followed_channels();
+                    Task::batch([self.start_refresh(token), followed_channels])
+                }
+                Some(token) => self.start_refresh(token),
+                None => Task::none(),
+            },
+            Message::ClickedStream(user_id) => {
+                let Some(this_stream) = self.followed_streams.get(&user_id).map(|followed_stream| followed_stream.stream.clone()) else {
+                    return Task::none();
+                };
                 let broadcaster_username = this_stream.user_login.to_string();
                 let broadcaster_displayname = this_stream.user_name.to_string();
                 let title = this_stream.title.to_string();
@@ -277,187 +1629,567 @@ impl IcedTwitchGui {
                     Message::GotChildProcessId,
                 )
             }
-            Message::SaveRefresh((followed_streams, handle_vec)) => {
-                self.followed_streams = followed_streams.clone();
-                self.image_handles = handle_vec.clone();
-                self.currently_streaming_broadcasters = self.followed_streams.clone().iter().map(|x| x.user_login.to_string()).collect();
+            Message::SaveRefresh(_, _) if self.token.is_none() => {
+                self.refresh_in_flight = false;
+                Task::none()
+            }
+            Message::SaveRefresh(_, followed_streams) => {
+                self.refresh_in_flight = false;
+                self.fetch_error = None;
+                self.offline = false;
+                self.now = SystemTime::now();
+                let snapshot = Snapshot {
+                    updated_at: SystemTime::now(),
+                    streams: followed_streams.clone(),
+                };
+                let _ = snapshot.save(&self.account_path());
+                self.last_updated = Some(snapshot.updated_at);
+                let mut old_streams = std::mem::take(&mut self.followed_streams);
+                self.stream_order = followed_streams.iter().map(|stream| stream.user_id.to_string()).collect();
+                for stream in followed_streams.iter() {
+                    // Keep the thumbnail we already have until the new one arrives.
+                    let thumbnail = match old_streams.remove(stream.user_id.as_str()) {
+                        Some(FollowedStream { thumbnail: ImageState::Loaded(handle), .. }) => ImageState::Loaded(handle),
+                        _ => ImageState::Loading,
+                    };
+                    self.followed_streams.insert(stream.user_id.to_string(), FollowedStream { stream: stream.clone(), thumbnail });
+                }
+                self.sort_streams();
+                self.currently_streaming_broadcasters = followed_streams.iter().map(|x| x.user_login.to_string()).collect();
+                Task::batch([
+                    get_thumnails(self.image_cache.clone(), followed_streams, self.thumbnail_concurrency),
+                    self.fetch_box_art(),
+                ])
+            }
+            Message::GotThumbnail(user_id, result) => {
+                let Some(followed_stream) = self.followed_streams.get_mut(&user_id) else {
+                    return Task::none();
+                };
+                match (result, &followed_stream.thumbnail) {
+                    (Ok(handle), _) => followed_stream.thumbnail = ImageState::Loaded(handle),
+                    // Keep showing an older thumbnail if there is one.
+                    (Err(_), ImageState::Loaded(_)) => {}
+                    (Err(error), thumbnail) => {
+                        let attempts = match thumbnail {
+                            ImageState::Failed { attempts, .. } => attempts + 1,
+                            _ => 1,
+                        };
+                        followed_stream.thumbnail = ImageState::Failed { error, attempts };
+                        if attempts <= MAX_THUMBNAIL_RETRIES {
+                            return retry_thumnail(self.image_cache.clone(), followed_stream.stream.clone(), attempts - 1);
+                        }
+                    }
+                }
                 Task::none()
             }
+            Message::RetryThumbnail(user_id) => {
+                let Some(followed_stream) = self.followed_streams.get_mut(&user_id) else {
+                    return Task::none();
+                };
+                followed_stream.thumbnail = ImageState::Loading;
+                Task::perform(
+                    get_thumnail(self.image_cache.clone(), followed_stream.stream.clone(), 320, 180),
+                    |(user_id, result)| Message::GotThumbnail(user_id, result),
+                )
+            }
+        }
+    }
+    fn placeholder_image() -> image::Image<image::Handle> {
+        image::Image::new(PLACEHOLDER_IMAGE.clone())
+            .width(320)
+            .height(180)
+            .opacity(0.3)
+    }
+    fn thumbnail(followed_stream: &FollowedStream) -> Element<'static, Message> {
+        match &followed_stream.thumbnail {
+            ImageState::Loaded(handle) => image::Image::new(handle.clone()).width(320).height(180).into(),
+            ImageState::Loading => Self::placeholder_image().into(),
+            ImageState::Failed { error, .. } => stack![
+                Self::placeholder_image(),
+                container(
+                    column![
+                        text(error.clone()).size(12),
+                        button(text("Retry").size(12))
+                            .padding([2, 6])
+                            .on_press(Message::RetryThumbnail(followed_stream.stream.user_id.to_string()))
+                    ]
+                    .spacing(5)
+                    .align_x(Center)
+                )
+                .center(Fill)
+            ]
+            .width(320)
+            .height(180)
+            .into(),
+        }
+    }
+    fn badge(label: String) -> container::Container<'static, Message> {
+        container(text(label).size(12))
+            .padding([2, 6])
+            .style(container::rounded_box)
+    }
+    /// The red badge on streams marked for mature audiences.
+    fn mature_badge() -> container::Container<'static, Message> {
+        container(text("18+").size(12).style(text::danger))
+            .padding([2, 6])
+            .style(container::rounded_box)
+    }
+    fn stream_card(followed_stream: &FollowedStream, now: SystemTime, favorite: bool, muted: bool) -> Element<'static, Message> {
+        let stream = &followed_stream.stream;
+        let user_id = stream.user_id.to_string();
+        let mut details = row![Self::badge(stream.language.to_uppercase())].spacing(5).align_y(Center);
+        if stream.is_mature {
+            details = details.push(Self::mature_badge());
         }
+        details = details.push(
+            text(stream.game_name.clone())
+                .wrapping(text::Wrapping::None)
+                .shaping(text::Shaping::Advanced),
+        );
+        let card = container(column![
+            stack![
+                Self::thumbnail(followed_stream),
+                container(row![
+                    Self::badge(format!("{} viewers", viewer_count_label(stream.viewer_count))),
+                    Space::with_width(Fill),
+                    Self::badge(uptime_label(stream.started_at.as_str(), now))
+                ])
+                .width(320)
+                .padding(5)
+            ],
+            row![
+                button(text(if favorite { "★" } else { "☆" }).shaping(text::Shaping::Advanced))
+                    .style(if favorite { button::primary } else { button::secondary })
+                    .on_press(Message::ToggleFavorite(user_id.clone())),
+                button("Play")
+                    .width(Shrink)
+                    .on_press(Message::ClickedStream(user_id.clone())),
+                button("Chat").on_press(Message::OpenChat(user_id.clone())),
+                button("Channel").on_press(Message::OpenChannel(user_id.clone())),
+                text(format!("@{}", stream.user_login))
+                    .wrapping(text::Wrapping::None)
+            ],
+            details,
+            text(stream.title.clone())
+                .wrapping(text::Wrapping::None)
+                .shaping(text::Shaping::Advanced),
+            row![
+                button(text("Hide from grid").size(12))
+                    .style(button::text)
+                    .on_press(Message::ToggleHidden(user_id.clone())),
+                button(text(if muted { "Unmute notifications" } else { "Mute notifications" }).size(12))
+                    .style(button::text)
+                    .on_press(Message::ToggleMuted(user_id.clone()))
+            ]
+        ])
+        .max_width(320)
+        .height(300)
+        .align_y(Top);
+        // The buttons on the card take their own clicks, anywhere else opens the details.
+        mouse_area(card)
+            .interaction(mouse::Interaction::Pointer)
+            .on_press(Message::OpenDetails(user_id))
+            .into()
     }
-    fn view(&self) -> Element<Message> {
-        if self.settings_open {
+    /// Lays out the cards for `user_ids` in rows of `num_columns`.
+    fn stream_grid(&self, user_ids: &[String]) -> iced_aw::Grid<'static, Message, iced::Theme, Renderer> {
+        let mut this_grid: iced_aw::Grid<'static, Message, iced::Theme, Renderer> =
+            iced_aw::Grid::new();
+        let followed_streams: Vec<&FollowedStream> = user_ids
+            .iter()
+            .filter_map(|user_id| self.followed_streams.get(user_id))
+            .collect();
+        for chunk in followed_streams.chunks(self.num_columns.max(1)) {
+            let mut this_grid_row: iced_aw::GridRow<'static, Message, iced::Theme, Renderer> =
+                iced_aw::GridRow::new();
+            for followed_stream in chunk {
+                this_grid_row = this_grid_row.push(Self::stream_card(
+                    followed_stream,
+                    self.now,
+                    self.favorites.contains(&followed_stream.stream.user_id.to_string()),
+                    self.muted.contains(followed_stream.stream.user_id.as_str()),
+                ));
+            }
+            this_grid = this_grid.push(this_grid_row);
+        }
+        this_grid
+    }
+    /// The cards for `user_ids` in a collapsible section per game, in the order each game first shows up.
+    fn game_sections(&self, user_ids: &[String]) -> Element<'static, Message> {
+        let mut games: Vec<(String, String, Vec<String>)> = Vec::new();
+        for user_id in user_ids {
+            let Some(followed_stream) = self.followed_streams.get(user_id) else {
+                continue;
+            };
+            let game_id = followed_stream.stream.game_id.to_string();
+            match games.iter_mut().find(|(id, _, _)| *id == game_id) {
+                Some((_, _, user_ids)) => user_ids.push(user_id.clone()),
+                None => {
+                    let game_name = match followed_stream.stream.game_name.as_str() {
+                        "" => String::from("No category"),
+                        game_name => game_name.to_string(),
+                    };
+                    games.push((game_id, game_name, vec![user_id.clone()]));
+                }
+            }
+        }
+        let mut sections = column![].spacing(10);
+        for (game_id, game_name, user_ids) in games {
+            let collapsed = self.collapsed_games.contains(&game_id);
+            let mut header = row![text(if collapsed { "▸" } else { "▾" })].spacing(10).align_y(Center);
+            if let Some(box_art) = self.box_art.get(&game_id) {
+                header = header.push(image::Image::new(box_art.clone()).width(52).height(72));
+            }
+            header = header.push(
+                text(format!("{game_name} ({})", user_ids.len()))
+                    .size(20)
+                    .shaping(text::Shaping::Advanced),
+            );
+            sections = sections.push(
+                button(header)
+                    .style(button::text)
+                    .width(Fill)
+                    .on_press(Message::ToggleGameSection(game_id)),
+            );
+            if !collapsed {
+                sections = sections.push(self.stream_grid(&user_ids));
+            }
+        }
+        sections.into()
+    }
+    /// One of the broadcaster lists on its management page, with a button to take each broadcaster off it.
+    fn broadcaster_list(title: &str, list: &BroadcasterList, remove_label: &str, on_remove: fn(String) -> Message) -> Element<'static, Message> {
+        let mut list_column = column![text(title.to_string()).size(20)].spacing(5);
+        if list.entries().is_empty() {
+            list_column = list_column.push(text("Nobody yet."));
+        }
+        for (user_id, login) in list.entries() {
+            let name = if login.is_empty() { user_id.clone() } else { format!("@{login}") };
+            list_column = list_column.push(
+                row![
+                    text(name).width(Fill),
+                    button(text(remove_label.to_string())).on_press(on_remove(user_id.clone()))
+                ]
+                .spacing(10)
+                .align_y(Center),
+            );
+        }
+        list_column.width(Fill).into()
+    }
+    /// The followed channels that aren't live, with what they played and when they were last live.
+    fn offline_channels(&self) -> Element<'static, Message> {
+        if self.followed_channels.is_empty() {
+            let status = if self.missing_features.contains(&Feature::AllFollows) {
+                "Grant permissions to see all the channels you follow."
+            } else if self.followed_channels_loading {
+                "Loading the channels you follow..."
+            } else {
+                "No followed channels."
+            };
+            return container(text(status)).center_x(Fill).padding(20).into();
+        }
+        let mut channels = column![].spacing(10).padding(10);
+        for channel in &self.followed_channels {
+            if self.followed_streams.contains_key(&channel.user_id) {
+                continue;
+            }
+            let avatar = match &channel.avatar {
+                Some(avatar) => image::Image::new(avatar.clone()),
+                None => image::Image::new(PLACEHOLDER_IMAGE.clone()).opacity(0.3),
+            };
+            let last_played = match channel.game_name.as_str() {
+                "" => String::from("Last played: nothing yet"),
+                game_name => format!("Last played: {game_name}"),
+            };
+            let last_live = match channel.last_broadcast {
+                Some(last_broadcast) => format!("Last live: {}", snapshot::updated_at_label(last_broadcast)),
+                None => String::from("Last live: unknown"),
+            };
+            channels = channels.push(
+                row![
+                    avatar.width(50).height(50),
+                    column![
+                        text(format!("{} (@{})", channel.display_name, channel.login)).shaping(text::Shaping::Advanced),
+                        text(last_played).size(12).shaping(text::Shaping::Advanced),
+                        text(last_live).size(12)
+                    ]
+                    .width(Fill),
+                    button("Channel").on_press(Message::OpenChannel(channel.user_id.clone()))
+                ]
+                .spacing(10)
+                .align_y(Center),
+            );
+        }
+        channels.into()
+    }
+    /// Everything about the stream in the detail panel, with the card's actions.
+    fn details_panel(&self, followed_stream: &FollowedStream) -> Element<'static, Message> {
+        let stream = &followed_stream.stream;
+        let user_id = stream.user_id.to_string();
+        let thumbnail: Element<'static, Message> = match (&self.details_thumbnail, &followed_stream.thumbnail) {
+            (ImageState::Loaded(handle), _) | (_, ImageState::Loaded(handle)) => {
+                image::Image::new(handle.clone()).width(640).height(360).into()
+            }
+            _ => image::Image::new(PLACEHOLDER_IMAGE.clone()).width(640).height(360).opacity(0.3).into(),
+        };
+        let mut tags = row![Self::badge(stream.language.to_uppercase())].spacing(5);
+        if stream.is_mature {
+            tags = tags.push(Self::mature_badge());
+        }
+        for tag in &stream.tags {
+            tags = tags.push(Self::badge(tag.clone()));
+        }
+        let started_at = match parse_timestamp(stream.started_at.as_str()) {
+            Some(started_at) => format!(
+                "Live since {} ({})",
+                snapshot::updated_at_label(started_at),
+                uptime_label(stream.started_at.as_str(), self.now)
+            ),
+            None => String::new(),
+        };
+        let description = match self.channel_descriptions.get(&user_id) {
+            Some(Ok(description)) if description.is_empty() => String::from("No channel description."),
+            Some(Ok(description)) => description.clone(),
+            Some(Err(error)) => error.clone(),
+            None => String::from("Loading the channel description..."),
+        };
+        container(
+            column![
+                thumbnail,
+                text(format!("{} (@{})", stream.user_name, stream.user_login))
+                    .size(24)
+                    .shaping(text::Shaping::Advanced),
+                text(stream.title.clone()).shaping(text::Shaping::Advanced),
+                text(stream.game_name.clone()).shaping(text::Shaping::Advanced),
+                tags.wrap(),
+                text(format!("{} viewers", viewer_count_label(stream.viewer_count))),
+                text(started_at),
+                text(description).size(14).shaping(text::Shaping::Advanced),
+                row![
+                    button("Play").on_press(Message::ClickedStream(user_id.clone())),
+                    button("Chat").on_press(Message::OpenChat(user_id.clone())),
+                    button("Channel").on_press(Message::OpenChannel(user_id)),
+                    Space::with_width(Fill),
+                    button("Close").on_press(Message::CloseDetails)
+                ]
+                .spacing(10)
+            ]
+            .spacing(10)
+            .width(640),
+        )
+        .padding(20)
+        .style(container::rounded_box)
+        .into()
+    }
+    fn account_picker(&self) -> Element<'_, Message> {
+        pick_list(
+            self.accounts.clone(),
+            Some(self.current_account.clone()),
+            Message::SwitchAccount,
+        )
+        .into()
+    }
+    fn view(&self) -> Element<'_, Message> {
+        if self.login_required {
+            let status = if let Some(device_code) = &self.device_code {
+                format!("Go to {} and enter the code below to sign in.", device_code.verification_uri)
+            } else if self.login_in_progress {
+                String::from("Waiting for you to authorize the app in your browser...")
+            } else {
+                String::from("You need to sign in to Twitch to see your followed streams.")
+            };
+            let mut login_column = column![Space::with_height(Fill), text(status)]
+                .spacing(10)
+                .align_x(Center);
+            if let Some(device_code) = &self.device_code {
+                login_column = login_column
+                    .push(text(device_code.user_code.clone()).size(40))
+                    .push(button("Open in browser").on_press(Message::OpenVerificationUri));
+            }
+            if let Some(error) = &self.login_error {
+                login_column = login_column.push(text(error.clone()));
+            }
+            let mut sign_in_button = button("Sign in again");
+            let mut device_sign_in_button = button("Sign in with a device code");
+            if !self.login_in_progress {
+                sign_in_button = sign_in_button.on_press(Message::StartLogin);
+                device_sign_in_button = device_sign_in_button.on_press(Message::StartDeviceLogin);
+            }
+            login_column = login_column
+                .push(row![sign_in_button, device_sign_in_button].spacing(10));
+            if self.accounts.len() > 1 {
+                login_column = login_column.push(self.account_picker());
+            }
+            login_column = login_column.push(Space::with_height(Fill));
+            container(login_column).center_x(Fill).into()
+        } else if self.settings_open && self.broadcaster_lists_open {
+            column![
+                row![
+                    Self::broadcaster_list("Hidden from the grid", &self.hidden, "Unhide", Message::ToggleHidden),
+                    Self::broadcaster_list("Muted notifications", &self.muted, "Unmute", Message::ToggleMuted)
+                ]
+                .spacing(20)
+                .padding(10),
+                Space::with_height(Fill),
+                container(button("Back").on_press(Message::BroadcasterListsToggle)).center_x(Fill)
+            ]
+            .into()
+        } else if self.settings_open {
             column![
                 Space::with_height(10), container(text("If both stream command an player command are filled, the stdout of the stream command will be piped to the player command.  If the stream command is filled out and the player command isn't, then only the stream command is run.  $title, $broadcaster_displayname, $broadcaster_username, and $oauth_token will be replaced with their respective values.  Escaped quotes may cause problems.")).center_x(Fill).padding(10), 
                 row![container(text("Stream command: ")).align_right(Fill).width(FillPortion(1)), container(text_input("Put your stream command here...", self.stream_command_input.as_str()).on_input(Message::StreamCommandTextInputChanged)).align_left(Fill).width(FillPortion(2)).padding(Padding::from([0, 10]))],
                 row![container(text("Player command: ")).align_right(Fill).width(FillPortion(1)), container(text_input("Put your player command here...", self.player_command_input.as_str()).on_input(Message::PlayerCommandTextInputChanged)).align_left(Fill).width(FillPortion(2)).padding(Padding::from([0, 10]))],
                 row![container(text("OAuth Token: ")).align_right(Fill).width(FillPortion(1)), container(text_input("Put the twitch oauth token from your browser here...", self.twitch_oauth_token_input.as_str()).on_input(Message::OAuthTokenTextInputChanged)).align_left(Fill).width(FillPortion(2)).padding(Padding::from([0, 10]))],
+                row![container(text("Helix base URL (restart to apply): ")).align_right(Fill).width(FillPortion(1)), container(text_input("https://api.twitch.tv/helix/", self.helix_url_input.as_str()).on_input(Message::HelixUrlTextInputChanged)).align_left(Fill).width(FillPortion(2)).padding(Padding::from([0, 10]))],
+                row![container(text("OAuth base URL (restart to apply): ")).align_right(Fill).width(FillPortion(1)), container(text_input(twitch_config::DEFAULT_OAUTH2_URL, self.oauth2_url_input.as_str()).on_input(Message::OAuth2UrlTextInputChanged)).align_left(Fill).width(FillPortion(2)).padding(Padding::from([0, 10]))],
+                row![container(text("Client ID (restart to apply): ")).align_right(Fill).width(FillPortion(1)), container(text_input(twitch_config::DEFAULT_CLIENT_ID, self.client_id_input.as_str()).on_input(Message::ClientIdTextInputChanged)).align_left(Fill).width(FillPortion(2)).padding(Padding::from([0, 10]))],
+                row![container(text("Proxy URL: ")).align_right(Fill).width(FillPortion(1)), container(text_input("http://proxy.example.com:8080 or socks5://127.0.0.1:1080", self.proxy_url_input.as_str()).on_input(Message::ProxyUrlTextInputChanged)).align_left(Fill).width(FillPortion(2)).padding(Padding::from([0, 10]))],
+                row![container(text("Extra CA certificates (PEM file): ")).align_right(Fill).width(FillPortion(1)), container(text_input("/path/to/ca-bundle.pem", self.ca_bundle_path_input.as_str()).on_input(Message::CaBundlePathTextInputChanged)).align_left(Fill).width(FillPortion(2)).padding(Padding::from([0, 10]))],
+                row![container(text("Sign-in redirect port: ")).align_right(Fill).width(FillPortion(1)), container(text_input("5454", self.redirect_port_input.as_str()).on_input(Message::RedirectPortTextInputChanged)).align_left(Fill).width(FillPortion(2)).padding(Padding::from([0, 10]))],
+                row![container(text("Parallel thumbnail downloads: ")).align_right(Fill).width(FillPortion(1)), container(text_input("8", self.thumbnail_concurrency_input.as_str()).on_input(Message::ThumbnailConcurrencyTextInputChanged)).align_left(Fill).width(FillPortion(2)).padding(Padding::from([0, 10]))],
+                row![container(text("Thumbnail cache time (minutes): ")).align_right(Fill).width(FillPortion(1)), container(text_input("5", self.image_cache_ttl_input.as_str()).on_input(Message::ImageCacheTtlTextInputChanged)).align_left(Fill).width(FillPortion(2)).padding(Padding::from([0, 10]))],
+                row![container(text("Image cache size (MB): ")).align_right(Fill).width(FillPortion(1)), container(text_input("100", self.image_cache_size_input.as_str()).on_input(Message::ImageCacheSizeTextInputChanged)).align_left(Fill).width(FillPortion(2)).padding(Padding::from([0, 10]))],
+                row![container(text("Favorite notification urgency: ")).align_right(Fill).width(FillPortion(1)), container(pick_list(Urgency::ALL, Some(self.favorite_urgency_input), Message::FavoriteUrgencySelected)).align_left(Fill).width(FillPortion(2)).padding(Padding::from([0, 10]))],
+                row![container(text("New account: ")).align_right(Fill).width(FillPortion(1)), container(row![text_input("Name for another Twitch account...", self.new_account_input.as_str()).on_input(Message::NewAccountTextInputChanged).on_submit(Message::AddAccount), button("Add account").on_press(Message::AddAccount)].spacing(10)).align_left(Fill).width(FillPortion(2)).padding(Padding::from([0, 10]))],
                 Space::with_height(Fill),
                 row![
+                container(button("Hidden and muted channels").on_press(Message::BroadcasterListsToggle)).center_x(Fill),
+                container(button("Sign out").on_press(Message::SignOut)).center_x(Fill),
                 container(button("Cancel").on_press(Message::SettingsToggle)).center_x(Fill),
                 container(button("Apply").on_press(Message::ApplySettings)).center_x(Fill)
             ].align_y(Bottom) 
             ]
             .into()
         } else {
-            let mut this_grid: iced_aw::Grid<'static, Message, iced::Theme, Renderer> =
-                iced_aw::Grid::new();
-            let row_length = self.num_columns;
-            let mut col_count = 0;
-            let num_streams = self.followed_streams.clone().len();
-            let mut this_grid_row: iced_aw::GridRow<'static, Message, iced::Theme, Renderer> =
-                iced_aw::GridRow::new();
-            for (i, stream) in self.followed_streams.clone().iter().enumerate() {
-                let stream_title = stream.title.clone();
-                if col_count < row_length {
-                    this_grid_row = this_grid_row.push(
-                        container(column![
-                            image::Image::new(self.image_handles[i].clone()),
-                            row![
-                                button("Play")
-                                    .width(Shrink)
-                                    .on_press(Message::ClickedStream(i)),
-                                button("Chat").on_press(Message::OpenChat(i)),
-                                button("Channel").on_press(Message::OpenChannel(i)),
-                                text(format!("@{}", stream.user_login))
-                                    .wrapping(text::Wrapping::None)
-                            ],
-                            text(stream.game_name.clone())
-                                .wrapping(text::Wrapping::None)
-                                .shaping(text::Shaping::Advanced),
-                            text(stream_title)
-                                .wrapping(text::Wrapping::None)
-                                .shaping(text::Shaping::Advanced)
-                        ])
-                        .max_width(320)
-                        .height(300)
-                        .align_y(Top),
-                    );
-                    col_count += 1;
-                    if i + 1 == num_streams {
-                        this_grid = this_grid.push(this_grid_row);
-                        this_grid_row = iced_aw::GridRow::new();
-                    }
-                } else {
-                    col_count = 1;
-                    this_grid = this_grid.push(this_grid_row);
-                    this_grid_row = iced_aw::GridRow::new();
-                    this_grid_row = this_grid_row.push(
-                        container(column![
-                            image::Image::new(self.image_handles[i].clone()),
-                            row![
-                                button("Play")
-                                    .width(Shrink)
-                                    .on_press(Message::ClickedStream(i)),
-                                button("Chat").on_press(Message::OpenChat(i)),
-                                button("Channel").on_press(Message::OpenChannel(i)),
-                                text(format!("@{}", stream.user_login))
-                                    .wrapping(text::Wrapping::None)
-                            ],
-                            text(stream.game_name.clone())
-                                .wrapping(text::Wrapping::None)
-                                .shaping(text::Shaping::Advanced),
-                            text(stream_title)
-                                .wrapping(text::Wrapping::None)
-                                .shaping(text::Shaping::Advanced)
-                        ])
-                        .max_width(320)
-                        .height(300)
-                        .align_y(Top),
-                    );
-                    if i + 1 == num_streams {
-                        this_grid = this_grid.push(this_grid_row);
-                        this_grid_row = iced_aw::GridRow::new();
-                    }
+            let (favorite_streams, other_streams): (Vec<String>, Vec<String>) = self
+                .visible_streams()
+                .into_iter()
+                .partition(|user_id| self.favorites.contains(user_id));
+            let other_grid: Element<'static, Message> = if self.group_by_game {
+                self.game_sections(&other_streams)
+            } else {
+                self.stream_grid(&other_streams).into()
+            };
+            let mut this_grid = column![].spacing(10);
+            if !favorite_streams.is_empty() {
+                this_grid = this_grid
+                    .push(text("Favorites").size(20))
+                    .push(self.stream_grid(&favorite_streams));
+            }
+            this_grid = this_grid.push(other_grid);
+            let tab_button = |label: &'static str, tab: Tab| {
+                button(label)
+                    .style(if self.tab == tab { button::primary } else { button::secondary })
+                    .on_press(Message::SelectTab(tab))
+            };
+            let tabs = row![tab_button("Live", Tab::Live), tab_button("All follows", Tab::AllFollows)].spacing(5);
+            let body: Element<'static, Message> = match self.tab {
+                Tab::Live => this_grid.into(),
+                Tab::AllFollows => self.offline_channels(),
+            };
+            let mut auth_row = row![text(self.auth_status.label())].spacing(10).align_y(Center);
+            if self.auth_status == AuthStatus::Invalid {
+                auth_row = auth_row.push(button("Sign in again").on_press(Message::StartLogin));
+            }
+            let mut permission_prompt = column![];
+            if !self.missing_features.is_empty() {
+                let feature_names: Vec<&str> = self.missing_features.iter().map(|feature| feature.name()).collect();
+                permission_prompt = permission_prompt.push(
+                    row![
+                        text(format!("These features need more permissions: {}", feature_names.join(", "))),
+                        button("Grant permissions").on_press_maybe((!self.login_in_progress).then_some(Message::GrantPermissions)),
+                        button("Dismiss").on_press(Message::DismissPermissionPrompt)
+                    ]
+                    .spacing(10)
+                    .align_y(Center),
+                );
+                if self.granting_permissions {
+                    let grant_status = match &self.device_code {
+                        Some(device_code) => row![
+                            text(format!("Go to {} and enter {} to grant the permissions.", device_code.verification_uri, device_code.user_code)),
+                            button("Open in browser").on_press(Message::OpenVerificationUri)
+                        ],
+                        None => row![text("Waiting for you to authorize the app in your browser...")],
+                    };
+                    permission_prompt = permission_prompt.push(grant_status.spacing(10).align_y(Center));
                 }
             }
-            column![
+            let mut offline_banner = column![];
+            if self.offline {
+                let status = match self.last_updated {
+                    Some(last_updated) => format!("Offline — last updated {}", snapshot::updated_at_label(last_updated)),
+                    None => String::from("Offline"),
+                };
+                offline_banner = offline_banner.push(text(status).style(text::secondary));
+            }
+            let mut error_banner = column![];
+            if let Some(error) = &self.fetch_error {
+                error_banner = error_banner.push(
+                    container(
+                        row![
+                            text(error.clone()).style(text::danger).width(Fill),
+                            button("Dismiss").on_press(Message::DismissError)
+                        ]
+                        .spacing(10)
+                        .align_y(Center),
+                    )
+                    .style(container::rounded_box)
+                    .padding(5),
+                );
+            }
+            let main_view = column![
                 row![
                     container(button("Settings").on_press(Message::SettingsToggle)).center_x(Fill),
+                    container(self.account_picker()).center_x(Fill),
+                    container(auth_row).center_x(Fill),
+                    container(
+                        text_input("Search (Ctrl+F)...", self.search_input.as_str())
+                            .id(SEARCH_INPUT)
+                            .on_input(Message::SearchTextInputChanged)
+                            .on_submit(Message::PlayTopMatch)
+                            .width(200)
+                    )
+                    .center_x(Fill),
+                    container(pick_list(SortOrder::ALL, Some(self.sort_order), Message::SortOrderSelected)).center_x(Fill),
+                    container(checkbox("Group by game", self.group_by_game).on_toggle(Message::GroupByGameToggled)).center_x(Fill),
                     container(button("Refresh").on_press(Message::Refresh)).center_x(Fill)
                 ],
-                scrollable(this_grid).anchor_top().width(Fill)
+                tabs,
+                permission_prompt,
+                offline_banner,
+                error_banner,
+                scrollable(body).anchor_top().width(Fill)
             ]
-            .align_x(Center)
-            .into()
+            .align_x(Center);
+            let Some(followed_stream) = self.details.as_ref().and_then(|user_id| self.followed_streams.get(user_id)) else {
+                return main_view.into();
+            };
+            // Clicking outside the panel closes it.
+            let backdrop = mouse_area(center(opaque(scrollable(self.details_panel(followed_stream)))).style(|_theme| {
+                container::Style {
+                    background: Some(Color { a: 0.8, ..Color::BLACK }.into()),
+                    ..container::Style::default()
+                }
+            }))
+            .on_press(Message::CloseDetails);
+            stack![main_view, opaque(backdrop)].into()
         }
     }
 }
 fn main() -> Result<(), iced::Error> {
     let mut p = home_dir().unwrap();
-    for e in vec![".cache", "iced_twitch_gui"] {
+    for e in [".cache", "iced_twitch_gui"] {
         p.push(e);
         if !p.exists() {
             std::fs::create_dir(p.clone()).unwrap();
         }
     }
-    if !home_dir()
-        .unwrap()
-        .join(".cache")
-        .join("iced_twitch_gui")
-        .join("access_token.txt")
-        .exists()
-    {
-        let mut token_builder = tokens::ImplicitUserTokenBuilder::new(
-            ClientId::from_static(CLIENT_ID),
-            "http://localhost:5454/redirect".parse().unwrap(),
-        );
-        let access_token_string: String;
-        token_builder = token_builder.set_scopes(vec![Scope::UserReadFollows]);
-        webbrowser::open(token_builder.generate_url().0.as_str()).unwrap();
-        let listener = TcpListener::bind("localhost:5454").unwrap();
-        for stream in listener.incoming() {
-            if let Ok(mut stream) = stream {
-                let mut reader = BufReader::new(&stream);
-
-                let mut request_line = String::new();
-                reader.read_line(&mut request_line).unwrap();
-                let redirect_url = request_line.split_whitespace().nth(1).unwrap();
-                //println!("{redirect_url}");
-                if redirect_url == "/redirect" {
-                    let message = include_str!("../redirect.html");
-                    let response = format!(
-                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
-                        message.len(),
-                        message
-                    );
-                    stream.write_all(response.as_bytes()).unwrap();
-                    stream.flush().unwrap();
-                    continue;
-                } else {
-                    let url =
-                        Url::parse(&("http://localhost:5454".to_string() + redirect_url)).unwrap();
-                    let code_pair = url
-                        .query_pairs()
-                        .find(|pair| {
-                            let &(ref key, _) = pair;
-                            key == "access_token"
-                        })
-                        .unwrap();
-
-                    let (_, value) = code_pair;
-                    access_token_string = value.to_string();
-                    //println!("Access token: '{access_token_string}'");
-                    let message = "You can now close this page";
-                    let message_length = message.len();
-                    stream.write_all(format!("HTTP/1.1 200 OK\r\nContent-Length: {message_length}\r\nContent-Type: text/plain\r\n\r\n{message}").as_bytes()).unwrap();
-                    let mut f = std::fs::File::create(
-                        home_dir()
-                            .unwrap()
-                            .join(".cache")
-                            .join("iced_twitch_gui")
-                            .join("access_token.txt"),
-                    )
-                    .unwrap();
-                    f.write_all(access_token_string.as_bytes()).unwrap();
-                    break;
-                }
-            }
-        }
-    }
-    let mut window_settings = iced::window::Settings::default();
-    window_settings.icon =
-        Some(iced::window::icon::from_file_data(include_bytes!("../icon.png"), None).unwrap());
+    let twitch_config = twitch_config::load(&p);
+    let network_config = network::load(&p);
+    let window_settings = iced::window::Settings {
+        icon: Some(iced::window::icon::from_file_data(include_bytes!("../icon.png"), None).unwrap()),
+        ..Default::default()
+    };
 
     iced::application(
         "Iced Twitch GUI",
@@ -468,17 +2200,34 @@ fn main() -> Result<(), iced::Error> {
     .window(window_settings)
     .subscription(IcedTwitchGui::subscription)
     .run_with(move || {
-        let mut c = IcedTwitchGui::new();
-        let d = c.update(Message::Startup(
-            std::fs::read_to_string(
-                home_dir()
-                    .unwrap()
-                    .join(".cache")
-                    .join("iced_twitch_gui")
-                    .join("access_token.txt"),
-            )
-            .unwrap(),
-        ));
+        let mut c = IcedTwitchGui::new(twitch_config, network_config);
+        let d = c.load_account();
         (c, d)
     })
 }
+
+#[cfg(test)]
+#[path = "bin/mock_twitch.rs"]
+#[allow(dead_code)]
+mod mock_twitch;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn gets_followed_streams_from_the_mock_server() {
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let base_url = format!("http://127.0.0.1:{}/", listener.local_addr().unwrap().port());
+        // Read once by twitch_api and twitch_oauth2, so this has to happen before the first request.
+        std::env::set_var(twitch_config::HELIX_URL_ENV, format!("{base_url}helix/"));
+        std::env::set_var(twitch_config::OAUTH2_URL_ENV, format!("{base_url}oauth2/"));
+        tokio::spawn(mock_twitch::serve(listener));
+        let client: HelixClient<ScheduledClient> = HelixClient::with_client(ScheduledClient::new(reqwest::Client::new()));
+        let token = get_user_token(client.clone(), String::from("mock-access-token")).await.unwrap();
+        assert_eq!(token.login.as_str(), "mockviewer");
+        let streams = get_followed_streams(client, token).await.unwrap();
+        let logins: Vec<&str> = streams.iter().map(|stream| stream.user_login.as_str()).collect();
+        assert_eq!(logins, ["mockstreamer", "speedrunner", "retrogamer", "pixelartist", "strawberryjam"]);
+    }
+}
@@ -0,0 +1,52 @@
+use crate::settings;
+use std::path::Path;
+
+/// How to reach the internet, as set in the cache directory.  Every request the app makes goes through the
+/// client built from this.
+#[derive(Clone, Debug, Default)]
+pub struct NetworkConfig {
+    pub proxy_url: String,
+    pub ca_bundle_path: String,
+}
+
+pub fn load(settings_path: &Path) -> NetworkConfig {
+    NetworkConfig {
+        proxy_url: settings::read(settings_path, "proxy_url.txt"),
+        ca_bundle_path: settings::read(settings_path, "ca_bundle_path.txt"),
+    }
+}
+
+impl NetworkConfig {
+    pub fn save(&self, settings_path: &Path) -> std::io::Result<()> {
+        std::fs::write(settings_path.join("proxy_url.txt"), self.proxy_url.trim())?;
+        std::fs::write(settings_path.join("ca_bundle_path.txt"), self.ca_bundle_path.trim())
+    }
+
+    /// Builds the client for Helix, OAuth and images, going through the proxy and trusting the extra CAs if set.
+    pub fn client(&self) -> Result<reqwest::Client, String> {
+        let mut builder = reqwest::Client::builder()
+            .user_agent(concat!("iced-twitch-gui/", env!("CARGO_PKG_VERSION")))
+            .redirect(reqwest::redirect::Policy::none());
+        let proxy_url = self.proxy_url.trim();
+        if !proxy_url.is_empty() {
+            let proxy = reqwest::Proxy::all(proxy_url).map_err(|e| format!("Invalid proxy URL {proxy_url}: {e}"))?;
+            builder = builder.proxy(proxy);
+        }
+        let ca_bundle_path = self.ca_bundle_path.trim();
+        if !ca_bundle_path.is_empty() {
+            let pem = std::fs::read(ca_bundle_path)
+                .map_err(|e| format!("Could not read the CA bundle {ca_bundle_path}: {e}"))?;
+            let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+                .map_err(|e| format!("Invalid CA bundle {ca_bundle_path}: {e}"))?;
+            if certificates.is_empty() {
+                return Err(format!("No certificates in the CA bundle {ca_bundle_path}"));
+            }
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        builder
+            .build()
+            .map_err(|e| format!("Could not set up the HTTP client: {e}"))
+    }
+}
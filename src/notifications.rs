@@ -0,0 +1,56 @@
+use std::fmt;
+use std::path::Path;
+
+/// How insistent a desktop notification is.  Only Linux and the BSDs take any notice.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    Critical,
+}
+
+impl Urgency {
+    pub const ALL: &'static [Urgency] = &[Urgency::Low, Urgency::Normal, Urgency::Critical];
+
+    fn setting(&self) -> &'static str {
+        match self {
+            Urgency::Low => "low",
+            Urgency::Normal => "normal",
+            Urgency::Critical => "critical",
+        }
+    }
+
+    pub fn load(settings_path: &Path, file_name: &str) -> Option<Self> {
+        let setting = std::fs::read_to_string(settings_path.join(file_name)).ok()?;
+        Urgency::ALL.iter().copied().find(|urgency| urgency.setting() == setting.trim())
+    }
+
+    pub fn save(&self, settings_path: &Path, file_name: &str) -> std::io::Result<()> {
+        std::fs::write(settings_path.join(file_name), self.setting())
+    }
+}
+
+impl fmt::Display for Urgency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Urgency::Low => "Low",
+            Urgency::Normal => "Normal",
+            Urgency::Critical => "Critical",
+        })
+    }
+}
+
+pub fn notify(body: &str, urgency: Urgency) {
+    let mut notification = notify_rust::Notification::new();
+    notification.summary("Iced Twitch GUI").body(body);
+    #[cfg(all(unix, not(target_os = "macos")))]
+    notification.urgency(match urgency {
+        Urgency::Low => notify_rust::Urgency::Low,
+        Urgency::Normal => notify_rust::Urgency::Normal,
+        Urgency::Critical => notify_rust::Urgency::Critical,
+    });
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    let _ = urgency;
+    let _ = notification.show();
+}
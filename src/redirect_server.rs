@@ -0,0 +1,246 @@
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use url::Url;
+
+pub const DEFAULT_PORT: u16 = 5454;
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+/// How long a connection gets to send its request line before it's dropped.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Why the redirect flow didn't produce an access token.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RedirectError {
+    Bind(String),
+    Browser(String),
+    TimedOut,
+    Denied { error: String, description: String },
+    StateMismatch,
+    MissingToken,
+}
+
+impl std::fmt::Display for RedirectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RedirectError::Bind(e) => write!(f, "Could not listen for the Twitch redirect: {e}"),
+            RedirectError::Browser(e) => write!(f, "Could not open the browser: {e}"),
+            RedirectError::TimedOut => write!(f, "Timed out waiting for the Twitch redirect."),
+            RedirectError::Denied { error, description } if description.is_empty() => {
+                write!(f, "Twitch sign-in failed: {error}")
+            }
+            RedirectError::Denied { description, .. } => write!(f, "Twitch sign-in failed: {description}"),
+            RedirectError::StateMismatch => {
+                write!(f, "The Twitch redirect didn't match this sign-in attempt.")
+            }
+            RedirectError::MissingToken => write!(f, "The Twitch redirect didn't include an access token."),
+        }
+    }
+}
+
+pub async fn bind(port: u16) -> Result<TcpListener, RedirectError> {
+    TcpListener::bind(("localhost", port))
+        .await
+        .map_err(|e| RedirectError::Bind(format!("localhost:{port}: {e}")))
+}
+
+/// What one request to the redirect server amounted to.
+enum Outcome {
+    Continue,
+    Done(Result<String, RedirectError>),
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) {
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Length: {}\r\nContent-Type: {content_type}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.flush().await;
+}
+
+fn message_page(title: &str, message: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head><title>{title}</title></head>\n<body style=\"text-align: center;\">\n<h1>{title}</h1>\n<p>{message}</p>\n</body>\n</html>"
+    )
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+async fn handle_connection(mut stream: TcpStream, expected_state: &str) -> Outcome {
+    let mut request_line = String::new();
+    let read = tokio::time::timeout(REQUEST_TIMEOUT, BufReader::new(&mut stream).read_line(&mut request_line)).await;
+    if !matches!(read, Ok(Ok(_))) {
+        return Outcome::Continue;
+    }
+    let Some(target) = request_line.split_whitespace().nth(1) else {
+        respond(&mut stream, "400 Bad Request", "text/html", &message_page("Bad request", "Malformed request.")).await;
+        return Outcome::Continue;
+    };
+    let Ok(url) = Url::parse("http://localhost").and_then(|base| base.join(target)) else {
+        respond(&mut stream, "400 Bad Request", "text/html", &message_page("Bad request", "Malformed request.")).await;
+        return Outcome::Continue;
+    };
+    let query = |key: &str| {
+        url.query_pairs()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.to_string())
+    };
+    match url.path() {
+        "/redirect" | "/token" => {}
+        _ => {
+            respond(&mut stream, "404 Not Found", "text/html", &message_page("Not found", "Nothing here.")).await;
+            return Outcome::Continue;
+        }
+    }
+    let state_matches = query("state").as_deref() == Some(expected_state);
+    let error = query("error");
+    if url.path() == "/redirect" && error.is_none() {
+        // The token is in the URL fragment, which only the browser can see, so hand it a page that sends it back as a query.
+        respond(&mut stream, "200 OK", "text/html", include_str!("../redirect.html")).await;
+        return Outcome::Continue;
+    }
+    if !state_matches {
+        // Any page can send the browser here, so only Twitch's own redirect gets to end the sign-in.
+        respond(&mut stream, "400 Bad Request", "text/html", &message_page("Sign-in failed", &RedirectError::StateMismatch.to_string())).await;
+        return Outcome::Continue;
+    }
+    if let Some(error) = error {
+        let description = query("error_description").unwrap_or_default();
+        let denied = RedirectError::Denied { error, description };
+        respond(&mut stream, "400 Bad Request", "text/html", &message_page("Sign-in failed", &escape_html(&denied.to_string()))).await;
+        return Outcome::Done(Err(denied));
+    }
+    match query("access_token") {
+        Some(access_token) => {
+            respond(&mut stream, "200 OK", "text/html", &message_page("Signed in", "You can now close this page.")).await;
+            Outcome::Done(Ok(access_token))
+        }
+        None => {
+            respond(&mut stream, "400 Bad Request", "text/html", &message_page("Sign-in failed", &RedirectError::MissingToken.to_string())).await;
+            Outcome::Done(Err(RedirectError::MissingToken))
+        }
+    }
+}
+
+/// Serves the redirect page on `listener` until Twitch hands back an access token for `expected_state`,
+/// an error, or `timeout` runs out.
+pub async fn wait_for_token(
+    listener: TcpListener,
+    expected_state: String,
+    timeout: Duration,
+) -> Result<String, RedirectError> {
+    let (done_sender, mut done) = tokio::sync::mpsc::unbounded_channel();
+    let serve = async {
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let Ok((stream, _)) = accepted else {
+                        continue;
+                    };
+                    // Browsers open idle preconnect sockets, so one connection mustn't hold up the others.
+                    let (expected_state, done_sender) = (expected_state.clone(), done_sender.clone());
+                    tokio::spawn(async move {
+                        if let Outcome::Done(result) = handle_connection(stream, &expected_state).await {
+                            let _ = done_sender.send(result);
+                        }
+                    });
+                }
+                Some(result) = done.recv() => return result,
+            }
+        }
+    };
+    tokio::time::timeout(timeout, serve)
+        .await
+        .unwrap_or(Err(RedirectError::TimedOut))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    const STATE: &str = "expected-state";
+
+    async fn start() -> (u16, tokio::task::JoinHandle<Result<String, RedirectError>>) {
+        let listener = bind(0).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(wait_for_token(listener, STATE.to_string(), Duration::from_secs(10)));
+        (port, server)
+    }
+
+    /// Sends `request_line` as-is and returns the status line of the response.
+    async fn send(port: u16, request_line: &str) -> String {
+        let mut stream = TcpStream::connect(("localhost", port)).await.unwrap();
+        stream.write_all(format!("{request_line}\r\nHost: localhost\r\n\r\n").as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response.lines().next().unwrap_or_default().to_string()
+    }
+
+    #[tokio::test]
+    async fn hands_back_the_access_token() {
+        let (port, server) = start().await;
+        let status = send(port, &format!("GET /token?access_token=abc123&state={STATE} HTTP/1.1")).await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(server.await.unwrap(), Ok(String::from("abc123")));
+    }
+
+    #[tokio::test]
+    async fn keeps_waiting_after_a_malformed_request() {
+        let (port, server) = start().await;
+        assert_eq!(send(port, "GARBAGE").await, "HTTP/1.1 400 Bad Request");
+        assert!(!server.is_finished());
+        send(port, &format!("GET /token?access_token=abc123&state={STATE} HTTP/1.1")).await;
+        assert_eq!(server.await.unwrap(), Ok(String::from("abc123")));
+    }
+
+    #[tokio::test]
+    async fn is_not_held_up_by_an_idle_connection() {
+        let (port, server) = start().await;
+        let _idle = TcpStream::connect(("localhost", port)).await.unwrap();
+        let status = send(port, &format!("GET /token?access_token=abc123&state={STATE} HTTP/1.1")).await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(server.await.unwrap(), Ok(String::from("abc123")));
+    }
+
+    #[tokio::test]
+    async fn reports_a_denied_sign_in() {
+        let (port, server) = start().await;
+        let status = send(port, &format!("GET /redirect?error=access_denied&error_description=The+user+denied+you+access&state={STATE} HTTP/1.1")).await;
+        assert_eq!(status, "HTTP/1.1 400 Bad Request");
+        assert_eq!(
+            server.await.unwrap(),
+            Err(RedirectError::Denied {
+                error: String::from("access_denied"),
+                description: String::from("The user denied you access"),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn ignores_redirects_with_the_wrong_state() {
+        let (port, server) = start().await;
+        assert_eq!(send(port, "GET /token?access_token=abc123&state=someone-else HTTP/1.1").await, "HTTP/1.1 400 Bad Request");
+        assert_eq!(send(port, "GET /redirect?error=access_denied HTTP/1.1").await, "HTTP/1.1 400 Bad Request");
+        assert!(!server.is_finished());
+        send(port, &format!("GET /token?access_token=abc123&state={STATE} HTTP/1.1")).await;
+        assert_eq!(server.await.unwrap(), Ok(String::from("abc123")));
+    }
+
+    #[tokio::test]
+    async fn rejects_a_missing_access_token() {
+        let (port, server) = start().await;
+        let status = send(port, &format!("GET /token?state={STATE} HTTP/1.1")).await;
+        assert_eq!(status, "HTTP/1.1 400 Bad Request");
+        assert_eq!(server.await.unwrap(), Err(RedirectError::MissingToken));
+    }
+
+    #[tokio::test]
+    async fn times_out_without_a_redirect() {
+        let listener = bind(0).await.unwrap();
+        let result = wait_for_token(listener, STATE.to_string(), Duration::from_millis(50)).await;
+        assert_eq!(result, Err(RedirectError::TimedOut));
+    }
+}
@@ -0,0 +1,176 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use twitch_api::client::{BoxedFuture, Request, Response};
+use twitch_api::HttpClient;
+
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// What the last response told us about the Helix rate limit bucket.
+#[derive(Default)]
+struct RateLimit {
+    remaining: Option<u64>,
+    reset: Option<SystemTime>,
+}
+
+impl RateLimit {
+    /// How long to wait before the bucket has room again, if it's empty.
+    fn wait(&self) -> Option<Duration> {
+        if self.remaining != Some(0) {
+            return None;
+        }
+        self.reset?.duration_since(SystemTime::now()).ok()
+    }
+    fn update(&mut self, response: &Response) {
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok())
+        };
+        if let Some(remaining) = header("Ratelimit-Remaining") {
+            self.remaining = Some(remaining);
+        }
+        if let Some(reset) = header("Ratelimit-Reset") {
+            self.reset = Some(UNIX_EPOCH + Duration::from_secs(reset));
+        }
+    }
+}
+
+/// The HTTP client behind `HelixClient`.  It waits for the rate limit bucket to refill before sending,
+/// and retries 429 and 5xx responses and connection errors with exponential backoff.
+#[derive(Clone)]
+pub struct ScheduledClient {
+    inner: reqwest::Client,
+    rate_limit: Arc<Mutex<RateLimit>>,
+}
+
+impl ScheduledClient {
+    pub fn new(inner: reqwest::Client) -> Self {
+        Self {
+            inner,
+            rate_limit: Arc::default(),
+        }
+    }
+}
+
+fn clone_request(request: &Request) -> Request {
+    let mut clone = http::Request::new(request.body().clone());
+    *clone.method_mut() = request.method().clone();
+    *clone.uri_mut() = request.uri().clone();
+    *clone.version_mut() = request.version();
+    *clone.headers_mut() = request.headers().clone();
+    clone
+}
+
+fn backoff(attempt: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF)
+}
+
+impl HttpClient for ScheduledClient {
+    type Error = reqwest::Error;
+
+    fn req(&self, request: Request) -> BoxedFuture<'_, Result<Response, Self::Error>> {
+        Box::pin(async move {
+            let mut attempt = 0;
+            loop {
+                let wait = self.rate_limit.lock().unwrap().wait();
+                if let Some(wait) = wait {
+                    tokio::time::sleep(wait.min(MAX_BACKOFF)).await;
+                }
+                let result = self.inner.req(clone_request(&request)).await;
+                attempt += 1;
+                let response = match result {
+                    Ok(response) => response,
+                    Err(e) if attempt >= MAX_ATTEMPTS => return Err(e),
+                    Err(_) => {
+                        tokio::time::sleep(backoff(attempt - 1)).await;
+                        continue;
+                    }
+                };
+                let status = response.status();
+                let retry_after = {
+                    let mut rate_limit = self.rate_limit.lock().unwrap();
+                    rate_limit.update(&response);
+                    if status == http::StatusCode::TOO_MANY_REQUESTS {
+                        rate_limit.remaining = Some(0);
+                        rate_limit.wait()
+                    } else {
+                        None
+                    }
+                };
+                if attempt >= MAX_ATTEMPTS
+                    || !(status == http::StatusCode::TOO_MANY_REQUESTS || status.is_server_error())
+                {
+                    return Ok(response);
+                }
+                if retry_after.is_none() {
+                    tokio::time::sleep(backoff(attempt - 1)).await;
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Serves one canned response per connection, in order, and counts the requests it saw.
+    fn serve(responses: Vec<String>) -> (String, Arc<AtomicUsize>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/helix/streams", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        std::thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                    line.clear();
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn response(status: &str, headers: &str) -> String {
+        format!("HTTP/1.1 {status}\r\n{headers}Content-Length: 0\r\nConnection: close\r\n\r\n")
+    }
+
+    #[tokio::test]
+    async fn retries_rate_limited_and_server_errors() {
+        let reset = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() + 2;
+        let (url, requests) = serve(vec![
+            response("429 Too Many Requests", &format!("Ratelimit-Remaining: 0\r\nRatelimit-Reset: {reset}\r\n")),
+            response("503 Service Unavailable", ""),
+            response("200 OK", "Ratelimit-Remaining: 799\r\n"),
+        ]);
+        let client = ScheduledClient::new(reqwest::Client::new());
+        let request = http::Request::get(url).body(Default::default()).unwrap();
+        let started = std::time::Instant::now();
+        let response = client.req(request).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        // Waits for the rate limit to reset (more than a second away) and then backs off after the 503.
+        let until_reset = UNIX_EPOCH + Duration::from_secs(reset);
+        assert!(SystemTime::now() >= until_reset + backoff(1));
+        assert!(started.elapsed() >= Duration::from_secs(1) + backoff(1));
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_limit() {
+        assert_eq!(backoff(0), INITIAL_BACKOFF);
+        assert_eq!(backoff(1), INITIAL_BACKOFF * 2);
+        assert_eq!(backoff(10), MAX_BACKOFF);
+    }
+}
@@ -0,0 +1,73 @@
+use twitch_api::twitch_oauth2::Scope;
+
+/// Something the app does that needs permission from the user.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Feature {
+    FollowedStreams,
+    AllFollows,
+}
+
+impl Feature {
+    /// The features every login asks for.  The others ask for their scopes the first time they're used.
+    pub const BASE: &'static [Feature] = &[Feature::FollowedStreams];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Feature::FollowedStreams => "Followed live streams",
+            Feature::AllFollows => "All followed channels",
+        }
+    }
+
+    pub fn scopes(&self) -> Vec<Scope> {
+        match self {
+            Feature::FollowedStreams => vec![Scope::UserReadFollows],
+            Feature::AllFollows => vec![Scope::UserReadFollows],
+        }
+    }
+
+    pub fn is_granted(&self, granted: &[Scope]) -> bool {
+        self.scopes().iter().all(|scope| granted.contains(scope))
+    }
+}
+
+/// The scopes needed by `features`, without duplicates.
+pub fn required_scopes(features: &[Feature]) -> Vec<Scope> {
+    let mut scopes: Vec<Scope> = Vec::new();
+    for scope in features.iter().flat_map(|feature| feature.scopes()) {
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    scopes
+}
+
+/// The `features` that can't be used with the `granted` scopes, without duplicates.
+pub fn missing_features(granted: &[Scope], features: &[Feature]) -> Vec<Feature> {
+    let mut missing: Vec<Feature> = Vec::new();
+    for feature in features {
+        if !feature.is_granted(granted) && !missing.contains(feature) {
+            missing.push(*feature);
+        }
+    }
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn required_scopes_leave_out_duplicates() {
+        assert_eq!(required_scopes(Feature::BASE), vec![Scope::UserReadFollows]);
+        assert_eq!(required_scopes(&[Feature::FollowedStreams, Feature::AllFollows]), vec![Scope::UserReadFollows]);
+        assert_eq!(required_scopes(&[]), Vec::<Scope>::new());
+    }
+
+    #[test]
+    fn missing_features_are_the_ones_without_their_scopes() {
+        let features = [Feature::FollowedStreams, Feature::AllFollows, Feature::AllFollows];
+        assert_eq!(missing_features(&[], &features), vec![Feature::FollowedStreams, Feature::AllFollows]);
+        assert_eq!(missing_features(&[Scope::ChatRead], &features), vec![Feature::FollowedStreams, Feature::AllFollows]);
+        assert_eq!(missing_features(&[Scope::UserReadFollows], &features), Vec::<Feature>::new());
+    }
+}
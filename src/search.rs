@@ -0,0 +1,40 @@
+use twitch_api::helix::streams::Stream;
+
+/// How well `term` matches `field`, both lowercase.  Substrings beat letters that are merely in order,
+/// and the closer together those letters are the better.
+fn term_score(term: &str, field: &str) -> Option<u32> {
+    if field.starts_with(term) {
+        return Some(200);
+    }
+    if field.contains(term) {
+        return Some(150);
+    }
+    let mut gaps = 0;
+    let mut field_chars = field.chars();
+    for c in term.chars() {
+        loop {
+            match field_chars.next() {
+                Some(f) if f == c => break,
+                Some(_) => gaps += 1,
+                None => return None,
+            }
+        }
+    }
+    Some(100u32.saturating_sub(gaps).max(1))
+}
+
+/// How well `query` matches `stream`, or `None` if it doesn't.  Every word of the query has to match
+/// the login, display name, game or title, ignoring case.
+pub fn score(query: &str, stream: &Stream) -> Option<u32> {
+    let fields = [
+        stream.user_login.as_str().to_lowercase(),
+        stream.user_name.as_str().to_lowercase(),
+        stream.game_name.to_lowercase(),
+        stream.title.to_lowercase(),
+    ];
+    query
+        .to_lowercase()
+        .split_whitespace()
+        .map(|term| fields.iter().filter_map(|field| term_score(term, field)).max())
+        .sum()
+}
@@ -0,0 +1,8 @@
+use std::path::Path;
+
+/// The trimmed contents of a settings file, or an empty string if there isn't one.
+pub fn read(settings_path: &Path, file_name: &str) -> String {
+    std::fs::read_to_string(settings_path.join(file_name))
+        .map(|value| value.trim().to_string())
+        .unwrap_or_default()
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::SystemTime;
+use twitch_api::helix::streams::Stream;
+
+const FILE_NAME: &str = "followed_streams.json";
+
+/// The followed streams from the last successful fetch, shown when Twitch can't be reached.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Snapshot {
+    pub updated_at: SystemTime,
+    pub streams: Vec<Stream>,
+}
+
+impl Snapshot {
+    pub fn load(account_path: &Path) -> Option<Self> {
+        let json = std::fs::read(account_path.join(FILE_NAME)).ok()?;
+        serde_json::from_slice(&json).ok()
+    }
+
+    pub fn save(&self, account_path: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_vec(self).map_err(std::io::Error::other)?;
+        std::fs::write(account_path.join(FILE_NAME), json)
+    }
+
+    pub fn remove(account_path: &Path) {
+        let _ = std::fs::remove_file(account_path.join(FILE_NAME));
+    }
+}
+
+/// `updated_at` as the local time of day, with the date if it wasn't today.
+pub fn updated_at_label(updated_at: SystemTime) -> String {
+    let updated_at = chrono::DateTime::<chrono::Local>::from(updated_at);
+    if updated_at.date_naive() == chrono::Local::now().date_naive() {
+        updated_at.format("%H:%M").to_string()
+    } else {
+        updated_at.format("%Y-%m-%d %H:%M").to_string()
+    }
+}
@@ -0,0 +1,92 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::path::Path;
+use twitch_api::helix::streams::Stream;
+
+/// How the live grid is ordered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    MostViewers,
+    FewestViewers,
+    RecentlyStarted,
+    LongestLive,
+    NameAToZ,
+    NameZToA,
+    GameAToZ,
+    GameZToA,
+}
+
+impl SortOrder {
+    pub const ALL: &'static [SortOrder] = &[
+        SortOrder::MostViewers,
+        SortOrder::FewestViewers,
+        SortOrder::RecentlyStarted,
+        SortOrder::LongestLive,
+        SortOrder::NameAToZ,
+        SortOrder::NameZToA,
+        SortOrder::GameAToZ,
+        SortOrder::GameZToA,
+    ];
+
+    /// What the order is saved as in `sort_order.txt`.
+    fn setting(&self) -> &'static str {
+        match self {
+            SortOrder::MostViewers => "viewers_desc",
+            SortOrder::FewestViewers => "viewers_asc",
+            SortOrder::RecentlyStarted => "started_desc",
+            SortOrder::LongestLive => "started_asc",
+            SortOrder::NameAToZ => "name_asc",
+            SortOrder::NameZToA => "name_desc",
+            SortOrder::GameAToZ => "game_asc",
+            SortOrder::GameZToA => "game_desc",
+        }
+    }
+
+    pub fn load(settings_path: &Path) -> Self {
+        let setting = std::fs::read_to_string(settings_path.join("sort_order.txt")).unwrap_or_default();
+        SortOrder::ALL
+            .iter()
+            .copied()
+            .find(|order| order.setting() == setting.trim())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, settings_path: &Path) -> std::io::Result<()> {
+        std::fs::write(settings_path.join("sort_order.txt"), self.setting())
+    }
+
+    /// Compares two streams in this order, falling back to the login so the order is stable between refreshes.
+    pub fn compare(&self, a: &Stream, b: &Stream) -> Ordering {
+        let name = |stream: &Stream| stream.user_name.as_str().to_lowercase();
+        let game = |stream: &Stream| stream.game_name.to_lowercase();
+        // RFC 3339 timestamps in UTC sort the same as the times they stand for.
+        let started_at = |stream: &Stream| stream.started_at.as_str().to_string();
+        let ordering = match self {
+            SortOrder::MostViewers => b.viewer_count.cmp(&a.viewer_count),
+            SortOrder::FewestViewers => a.viewer_count.cmp(&b.viewer_count),
+            SortOrder::RecentlyStarted => started_at(b).cmp(&started_at(a)),
+            SortOrder::LongestLive => started_at(a).cmp(&started_at(b)),
+            SortOrder::NameAToZ => name(a).cmp(&name(b)),
+            SortOrder::NameZToA => name(b).cmp(&name(a)),
+            SortOrder::GameAToZ => game(a).cmp(&game(b)),
+            SortOrder::GameZToA => game(b).cmp(&game(a)),
+        };
+        ordering.then_with(|| a.user_login.as_str().cmp(b.user_login.as_str()))
+    }
+}
+
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SortOrder::MostViewers => "Most viewers",
+            SortOrder::FewestViewers => "Fewest viewers",
+            SortOrder::RecentlyStarted => "Recently started",
+            SortOrder::LongestLive => "Longest live",
+            SortOrder::NameAToZ => "Name (A-Z)",
+            SortOrder::NameZToA => "Name (Z-A)",
+            SortOrder::GameAToZ => "Game (A-Z)",
+            SortOrder::GameZToA => "Game (Z-A)",
+        })
+    }
+}
@@ -0,0 +1,84 @@
+use crate::settings;
+use std::path::Path;
+
+pub const DEFAULT_CLIENT_ID: &str = "reh9rt391dkrperi4b7cqelryifsej";
+pub const DEFAULT_OAUTH2_URL: &str = "https://id.twitch.tv/oauth2/";
+/// Read by `twitch_api` for the Helix base URL.
+pub const HELIX_URL_ENV: &str = "TWITCH_HELIX_URL";
+/// Read by `twitch_oauth2` for the OAuth base URL.
+pub const OAUTH2_URL_ENV: &str = "TWITCH_OAUTH2_URL";
+pub const CLIENT_ID_ENV: &str = "ICED_TWITCH_GUI_CLIENT_ID";
+
+/// Where the app talks to Twitch, as set in the environment or in the cache directory.
+#[derive(Clone, Debug)]
+pub struct TwitchConfig {
+    pub helix_url: String,
+    pub oauth2_url: String,
+    pub client_id: String,
+}
+
+/// Parses `url` as a base URL, adding the trailing slash relative paths need.
+fn normalize_base_url(url: &str) -> Option<String> {
+    let url = url.trim();
+    if url.is_empty() {
+        return None;
+    }
+    let url = if url.ends_with('/') { url.to_string() } else { format!("{url}/") };
+    url::Url::parse(&url).ok().map(|_| url)
+}
+
+/// Works out the base URLs and client ID, environment first, then settings files.
+///
+/// The URLs are exported to the environment variables `twitch_api` and `twitch_oauth2` read, so this has to run
+/// before the first request is made.
+pub fn load(settings_path: &Path) -> TwitchConfig {
+    let base_url = |env: &str, file_name: &str| {
+        let url = std::env::var(env)
+            .ok()
+            .and_then(|url| normalize_base_url(&url))
+            .or_else(|| normalize_base_url(&settings::read(settings_path, file_name)));
+        match &url {
+            Some(url) => std::env::set_var(env, url),
+            None => std::env::remove_var(env),
+        }
+        url.unwrap_or_default()
+    };
+    let helix_url = base_url(HELIX_URL_ENV, "helix_url.txt");
+    let oauth2_url = base_url(OAUTH2_URL_ENV, "oauth2_url.txt");
+    let client_id = std::env::var(CLIENT_ID_ENV)
+        .ok()
+        .filter(|client_id| !client_id.trim().is_empty())
+        .unwrap_or(settings::read(settings_path, "client_id.txt"));
+    TwitchConfig {
+        helix_url,
+        oauth2_url,
+        client_id,
+    }
+}
+
+impl TwitchConfig {
+    pub fn client_id(&self) -> String {
+        if self.client_id.is_empty() {
+            String::from(DEFAULT_CLIENT_ID)
+        } else {
+            self.client_id.clone()
+        }
+    }
+
+    pub fn oauth2_url(&self) -> String {
+        if self.oauth2_url.is_empty() {
+            String::from(DEFAULT_OAUTH2_URL)
+        } else {
+            self.oauth2_url.clone()
+        }
+    }
+
+    /// Saves the settings for the next start.  Empty values go back to the defaults.
+    pub fn save(&self, settings_path: &Path) -> std::io::Result<()> {
+        let helix_url = normalize_base_url(&self.helix_url).unwrap_or_default();
+        let oauth2_url = normalize_base_url(&self.oauth2_url).unwrap_or_default();
+        std::fs::write(settings_path.join("helix_url.txt"), helix_url)?;
+        std::fs::write(settings_path.join("oauth2_url.txt"), oauth2_url)?;
+        std::fs::write(settings_path.join("client_id.txt"), self.client_id.trim())
+    }
+}